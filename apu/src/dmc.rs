@@ -0,0 +1,163 @@
+const RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+/// Delta modulation channel, playing 1 bit delta encoded samples straight from
+/// CPU memory.
+///
+/// The channel can't reach the bus itself, it asks for a byte through
+/// [`Dmc::fetch_address`] and the owner of the bus answers with [`Dmc::load`].
+#[derive(Debug)]
+pub struct Dmc {
+    pub irq_enabled: bool,
+    pub irq: bool,
+    looping: bool,
+    timer_period: u16,
+    timer: u16,
+
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+    level: u8,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Self {
+            irq_enabled: false,
+            irq: false,
+            looping: false,
+            timer_period: RATE_TABLE[0],
+            timer: 0,
+
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+
+            shift: 0,
+            bits_remaining: 8,
+            silence: true,
+            level: 0,
+        }
+    }
+}
+
+impl Dmc {
+    /// Handles a write to $4010-$4013.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.irq_enabled = data & 0x80 != 0;
+                self.looping = data & 0x40 != 0;
+                self.timer_period = RATE_TABLE[(data & 0x0F) as usize];
+
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+            }
+            1 => {
+                self.level = data & 0x7F;
+            }
+            2 => {
+                self.sample_address = 0xC000 | ((data as u16) << 6);
+            }
+            3 => {
+                self.sample_length = ((data as u16) << 4) + 1;
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Handles bit 4 of a $4015 write.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    /// Address the memory reader wants to fetch, if the sample buffer needs refilling.
+    pub fn fetch_address(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    /// Fills the sample buffer with the byte read from [`Dmc::fetch_address`].
+    pub fn load(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+
+        // The address wraps around to $8000 rather than $0000
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle, the rate table is in CPU cycles.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+
+        if !self.silence {
+            if self.shift & 0x01 != 0 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+
+        self.shift >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift = data;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+}
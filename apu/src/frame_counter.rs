@@ -0,0 +1,84 @@
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameClock {
+    pub quarter: bool,
+    pub half: bool,
+}
+
+/// Sequencer written through $4017, counted in CPU cycles.
+#[derive(Debug, Default)]
+pub struct FrameCounter {
+    five_step: bool,
+    irq_inhibit: bool,
+    pub irq: bool,
+    cycle: u32,
+    /// CPU cycles left before a $4017 write resets the sequencer
+    reset_delay: u8,
+}
+
+impl FrameCounter {
+    pub fn write(&mut self, data: u8, odd_cycle: bool) {
+        self.five_step = data & 0x80 != 0;
+        self.irq_inhibit = data & 0x40 != 0;
+
+        if self.irq_inhibit {
+            self.irq = false;
+        }
+
+        // The reset lands 3 CPU cycles later when written on an even cycle, 4 on an odd one
+        self.reset_delay = if odd_cycle { 4 } else { 3 };
+    }
+
+    pub fn tick(&mut self) -> FrameClock {
+        let mut clock = FrameClock::default();
+
+        if self.reset_delay > 0 {
+            self.reset_delay -= 1;
+
+            if self.reset_delay == 0 {
+                self.cycle = 0;
+
+                // Switching to 5-step mode clocks everything immediately
+                if self.five_step {
+                    clock.quarter = true;
+                    clock.half = true;
+                }
+
+                return clock;
+            }
+        }
+
+        self.cycle += 1;
+
+        match (self.cycle, self.five_step) {
+            (7457, _) | (22371, _) => clock.quarter = true,
+            (14913, _) => {
+                clock.quarter = true;
+                clock.half = true;
+            }
+            (29828, false) => self.set_irq(),
+            (29829, false) => {
+                clock.quarter = true;
+                clock.half = true;
+                self.set_irq();
+            }
+            (29830, false) => {
+                self.set_irq();
+                self.cycle = 0;
+            }
+            (37281, true) => {
+                clock.quarter = true;
+                clock.half = true;
+            }
+            (37282, true) => self.cycle = 0,
+            _ => {}
+        }
+
+        clock
+    }
+
+    fn set_irq(&mut self) {
+        if !self.irq_inhibit {
+            self.irq = true;
+        }
+    }
+}
@@ -0,0 +1,180 @@
+mod dmc;
+mod envelope;
+mod frame_counter;
+mod mixer;
+mod noise;
+mod pulse;
+mod resampler;
+mod triangle;
+
+use dmc::Dmc;
+use frame_counter::FrameCounter;
+use mixer::Mixer;
+use noise::Noise;
+use pulse::Pulse;
+use resampler::Resampler;
+use triangle::Triangle;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct APU {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+    odd_cycle: bool,
+    mixer: Mixer,
+    /// Cartridge audio for the current cycle, already on the mixer's scale
+    expansion: f32,
+    resampler: Resampler,
+}
+
+impl Default for APU {
+    fn default() -> Self {
+        Self {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            frame_counter: FrameCounter::default(),
+            odd_cycle: false,
+            mixer: Mixer::default(),
+            expansion: 0.0,
+            resampler: Resampler::default(),
+        }
+    }
+}
+
+impl APU {
+    pub fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x4015 => {
+                let data = (self.pulse1.length.active() as u8)
+                    | (self.pulse2.length.active() as u8) << 1
+                    | (self.triangle.length.active() as u8) << 2
+                    | (self.noise.length.active() as u8) << 3
+                    | (self.dmc.active() as u8) << 4
+                    | (self.frame_counter.irq as u8) << 6
+                    | (self.dmc.irq as u8) << 7;
+
+                self.frame_counter.irq = false;
+
+                data
+            }
+            _ => 0x00,
+        }
+    }
+
+    pub fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4000..=0x4003 => self.pulse1.write(address, data),
+            0x4004..=0x4007 => self.pulse2.write(address, data),
+            0x4008..=0x400B => self.triangle.write(address, data),
+            0x400C..=0x400F => self.noise.write(address, data),
+            0x4010..=0x4013 => self.dmc.write(address, data),
+            0x4015 => {
+                self.pulse1.length.set_enabled(data & 0x01 != 0);
+                self.pulse2.length.set_enabled(data & 0x02 != 0);
+                self.triangle.length.set_enabled(data & 0x04 != 0);
+                self.noise.length.set_enabled(data & 0x08 != 0);
+                self.dmc.set_enabled(data & 0x10 != 0);
+            }
+            0x4017 => self.frame_counter.write(data, self.odd_cycle),
+            _ => {}
+        }
+    }
+
+    /// Level of the IRQ line going to the CPU.
+    pub fn irq(&self) -> bool {
+        self.frame_counter.irq || self.dmc.irq
+    }
+
+    /// Address the DMC wants read from the CPU bus before its sample buffer runs dry.
+    pub fn dmc_fetch_address(&self) -> Option<u16> {
+        self.dmc.fetch_address()
+    }
+
+    /// Hands the byte read from [`APU::dmc_fetch_address`] back to the DMC.
+    pub fn dmc_load(&mut self, data: u8) {
+        self.dmc.load(data);
+    }
+
+    /// Advances the APU by one CPU cycle.
+    pub fn tick(&mut self) {
+        self.triangle.clock_timer();
+        self.dmc.clock_timer();
+
+        if self.odd_cycle {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+            self.noise.clock_timer();
+        }
+
+        let clock = self.frame_counter.tick();
+
+        if clock.quarter {
+            self.pulse1.envelope.clock();
+            self.pulse2.envelope.clock();
+            self.noise.envelope.clock();
+            self.triangle.clock_linear();
+        }
+
+        if clock.half {
+            self.pulse1.length.clock();
+            self.pulse2.length.clock();
+            self.triangle.length.clock();
+            self.noise.length.clock();
+            self.pulse1.clock_sweep();
+            self.pulse2.clock_sweep();
+        }
+
+        self.odd_cycle = !self.odd_cycle;
+
+        let amplitude = self.mixer.mix(self.channel_outputs()) + self.expansion;
+        self.resampler.push(amplitude);
+    }
+
+    /// Sets the level of the cartridge's expansion audio, mixed in from the next `tick` on.
+    pub fn set_expansion_output(&mut self, level: f32) {
+        self.expansion = level;
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.resampler.sample_rate()
+    }
+
+    /// Changes the host sample rate, dropping any samples not drained yet.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.resampler = Resampler::new(sample_rate);
+    }
+
+    /// Speeds up or slows down sample production by a small factor, see
+    /// `Resampler::set_rate_adjustment`. Used for dynamic rate control.
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        self.resampler.set_rate_adjustment(adjustment);
+    }
+
+    /// Number of samples ready to be drained.
+    pub fn audio_available(&self) -> usize {
+        self.resampler.available()
+    }
+
+    /// Moves queued mono samples into `out`, returning how many were written.
+    pub fn drain_audio(&mut self, out: &mut [f32]) -> usize {
+        self.resampler.drain(out)
+    }
+
+    /// Current output levels of pulse 1, pulse 2, triangle, noise (4 bit) and DMC (7 bit).
+    pub fn channel_outputs(&self) -> [u8; 5] {
+        [
+            self.pulse1.output(),
+            self.pulse2.output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc.output(),
+        ]
+    }
+}
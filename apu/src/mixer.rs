@@ -0,0 +1,35 @@
+/// Non-linear DAC of the 2A03, using the lookup table approximation from the NESdev wiki.
+#[derive(Debug)]
+pub struct Mixer {
+    pulse_table: [f32; 31],
+    tnd_table: [f32; 203],
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        let mut pulse_table = [0.0; 31];
+        let mut tnd_table = [0.0; 203];
+
+        for (n, level) in pulse_table.iter_mut().enumerate().skip(1) {
+            *level = 95.52 / (8128.0 / n as f32 + 100.0);
+        }
+
+        for (n, level) in tnd_table.iter_mut().enumerate().skip(1) {
+            *level = 163.67 / (24329.0 / n as f32 + 100.0);
+        }
+
+        Self {
+            pulse_table,
+            tnd_table,
+        }
+    }
+}
+
+impl Mixer {
+    /// Mixes the channel levels returned by `APU::channel_outputs` into roughly 0.0..=1.0.
+    pub fn mix(&self, outputs: [u8; 5]) -> f32 {
+        let [pulse1, pulse2, triangle, noise, dmc] = outputs.map(|level| level as usize);
+
+        self.pulse_table[pulse1 + pulse2] + self.tnd_table[3 * triangle + 2 * noise + dmc]
+    }
+}
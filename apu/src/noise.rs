@@ -0,0 +1,74 @@
+use crate::envelope::{Envelope, LengthCounter};
+
+const PERIOD_TABLE: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+#[derive(Debug)]
+pub struct Noise {
+    short_mode: bool,
+    timer_period: u16,
+    timer: u16,
+    shift: u16,
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            short_mode: false,
+            timer_period: PERIOD_TABLE[0],
+            timer: 0,
+            shift: 0x0001,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+        }
+    }
+}
+
+impl Noise {
+    /// Handles a write to $400C-$400F.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x03 {
+            0 => {
+                self.length.set_halt(data & 0x20 != 0);
+                self.envelope.write(data);
+            }
+            1 => {}
+            2 => {
+                self.short_mode = data & 0x80 != 0;
+                // The table is in CPU cycles, the timer runs at APU cycles
+                self.timer_period = PERIOD_TABLE[(data & 0x0F) as usize] / 2;
+            }
+            3 => {
+                self.length.load(data >> 3);
+                self.envelope.restart();
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Clocked every APU cycle (every other CPU cycle).
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+
+        // Mode 1 taps bit 6 instead of bit 1, giving the short 93 step metallic sequence
+        let tap = if self.short_mode { 6 } else { 1 };
+        let feedback = (self.shift & 0x01) ^ ((self.shift >> tap) & 0x01);
+        self.shift = (self.shift >> 1) | (feedback << 14);
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.active() || self.shift & 0x01 != 0 {
+            return 0;
+        }
+
+        self.envelope.output()
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+pub const NTSC_CPU_CLOCK: f64 = 1_789_773.0;
+
+/// Taps of the band-limited step, the output lags the input by half of this
+const KERNEL_WIDTH: usize = 16;
+/// Sub-sample positions the kernel is precomputed for
+const KERNEL_PHASES: usize = 64;
+/// Cutoff of the step's sinc, as a fraction of the output Nyquist frequency
+const KERNEL_CUTOFF: f64 = 0.9;
+/// One second at the highest sample rate we expect, older samples get dropped
+const MAX_QUEUED: usize = 48_000;
+
+#[derive(Debug, Default)]
+struct HighPass {
+    alpha: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl HighPass {
+    fn new(cutoff: f64, sample_rate: f64) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+
+        Self {
+            alpha: (rc / (rc + dt)) as f32,
+            ..Default::default()
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.prev_output = self.alpha * (self.prev_output + input - self.prev_input);
+        self.prev_input = input;
+
+        self.prev_output
+    }
+}
+
+#[derive(Debug, Default)]
+struct LowPass {
+    alpha: f32,
+    prev_output: f32,
+}
+
+impl LowPass {
+    fn new(cutoff: f64, sample_rate: f64) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+
+        Self {
+            alpha: (dt / (rc + dt)) as f32,
+            ..Default::default()
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        self.prev_output += self.alpha * (input - self.prev_output);
+
+        self.prev_output
+    }
+}
+
+/// Turns the per CPU cycle mixer output into PCM at the host sample rate.
+///
+/// Every change in amplitude is inserted as a band-limited step (a windowed
+/// sinc integrated over one output sample), so the 1.79 MHz square waves don't
+/// alias. The result then goes through the two high-pass and one low-pass
+/// filters found on the console's audio path.
+#[derive(Debug)]
+pub struct Resampler {
+    sample_rate: u32,
+    /// Output samples per CPU cycle
+    ratio: f64,
+    /// Position of the current CPU cycle in output samples, relative to `deltas[0]`
+    time: f64,
+    amplitude: f32,
+    integrator: f32,
+    kernel: Vec<[f32; KERNEL_WIDTH]>,
+    deltas: VecDeque<f32>,
+
+    high_pass_90: HighPass,
+    high_pass_440: HighPass,
+    low_pass_14k: LowPass,
+
+    samples: VecDeque<f32>,
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Self::new(44_100)
+    }
+}
+
+impl Resampler {
+    pub fn new(sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+
+        Self {
+            sample_rate,
+            ratio: rate / NTSC_CPU_CLOCK,
+            time: 0.0,
+            amplitude: 0.0,
+            integrator: 0.0,
+            kernel: Self::build_kernel(),
+            deltas: VecDeque::from(vec![0.0; KERNEL_WIDTH]),
+
+            high_pass_90: HighPass::new(90.0, rate),
+            high_pass_440: HighPass::new(440.0, rate),
+            low_pass_14k: LowPass::new(14_000.0, rate),
+
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Nudges the output rate by a small factor (e.g. `0.005` for +0.5%) without
+    /// touching the filters, so a frontend can keep its audio buffer level steady.
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        self.ratio = self.sample_rate as f64 * (1.0 + adjustment) / NTSC_CPU_CLOCK;
+    }
+
+    fn build_kernel() -> Vec<[f32; KERNEL_WIDTH]> {
+        const STEPS: usize = 32;
+        let center = (KERNEL_WIDTH / 2) as f64;
+
+        // Blackman windowed sinc, the derivative of the step we want
+        let impulse = |x: f64| -> f64 {
+            if x.abs() >= center {
+                return 0.0;
+            }
+
+            let sinc = if x == 0.0 {
+                KERNEL_CUTOFF
+            } else {
+                (PI * KERNEL_CUTOFF * x).sin() / (PI * x)
+            };
+            let w = (x + center) / (2.0 * center);
+            let window = 0.42 - 0.5 * (2.0 * PI * w).cos() + 0.08 * (4.0 * PI * w).cos();
+
+            sinc * window
+        };
+
+        (0..KERNEL_PHASES)
+            .map(|phase| {
+                let frac = phase as f64 / KERNEL_PHASES as f64;
+                let mut taps = [0.0_f64; KERNEL_WIDTH];
+
+                // Each tap holds how much of the step happens within that output sample
+                for (i, tap) in taps.iter_mut().enumerate() {
+                    let start = i as f64 - 1.0 - frac - center;
+                    *tap = (0..STEPS)
+                        .map(|s| impulse(start + (s as f64 + 0.5) / STEPS as f64))
+                        .sum::<f64>()
+                        / STEPS as f64;
+                }
+
+                // Normalise so every phase adds up to a full step with no DC error
+                let total: f64 = taps.iter().sum();
+                taps.map(|tap| (tap / total) as f32)
+            })
+            .collect()
+    }
+
+    /// Feeds the mixer output for one CPU cycle.
+    pub fn push(&mut self, amplitude: f32) {
+        let delta = amplitude - self.amplitude;
+
+        if delta != 0.0 {
+            self.amplitude = amplitude;
+
+            let phase = (self.time.fract() * KERNEL_PHASES as f64) as usize;
+            let offset = self.time as usize;
+
+            for (i, tap) in self.kernel[phase].iter().enumerate() {
+                self.deltas[offset + i] += delta * tap;
+            }
+        }
+
+        self.time += self.ratio;
+
+        // No step inserted from now on can reach the front sample anymore
+        while self.time >= 1.0 {
+            self.time -= 1.0;
+
+            let delta = self.deltas.pop_front().unwrap_or_default();
+            self.deltas.push_back(0.0);
+            self.integrator += delta;
+
+            let mut sample = self.high_pass_90.process(self.integrator);
+            sample = self.high_pass_440.process(sample);
+            sample = self.low_pass_14k.process(sample);
+
+            if self.samples.len() >= MAX_QUEUED {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(sample);
+        }
+    }
+
+    pub fn available(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Moves as many queued samples as fit into `out`, returning how many were written.
+    pub fn drain(&mut self, out: &mut [f32]) -> usize {
+        let count = out.len().min(self.samples.len());
+
+        for (dst, src) in out.iter_mut().zip(self.samples.drain(..count)) {
+            *dst = src;
+        }
+
+        count
+    }
+}
@@ -0,0 +1,90 @@
+use std::{fmt, io};
+
+/// Part of the ROM image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RomSection {
+    /// The 16 byte iNES header
+    Header,
+    /// 512 bytes in front of the PRG-ROM
+    Trainer,
+    Prg,
+    Chr,
+}
+
+impl fmt::Display for RomSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomSection::Header => write!(f, "header"),
+            RomSection::Trainer => write!(f, "trainer"),
+            RomSection::Prg => write!(f, "PRG-ROM"),
+            RomSection::Chr => write!(f, "CHR-ROM"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    /// The file doesn't start with `NES\x1A`
+    BadMagic([u8; 4]),
+    /// The file ends before the sizes in the header say it should
+    Truncated {
+        section: RomSection,
+        expected: usize,
+        found: usize,
+    },
+    UnsupportedMapper {
+        mapper: u16,
+        submapper: u8,
+    },
+    /// The header describes something no cartridge can be
+    InconsistentHeader(&'static str),
+    /// Valid in the header, but more than the 255 banks the mappers can address
+    TooLarge {
+        memory: &'static str,
+        size: usize,
+    },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Io(err) => write!(f, "couldn't read the ROM: {err}"),
+            CartridgeError::BadMagic(name) => {
+                write!(f, "not an iNES ROM, the file starts with {name:02X?}")
+            }
+            CartridgeError::Truncated {
+                section,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{section} is cut short, expected {expected} bytes but found {found}"
+            ),
+            CartridgeError::UnsupportedMapper { mapper, submapper } => {
+                write!(f, "mapper {mapper} (submapper {submapper}) isn't supported")
+            }
+            CartridgeError::InconsistentHeader(reason) => {
+                write!(f, "inconsistent header: {reason}")
+            }
+            CartridgeError::TooLarge { memory, size } => {
+                write!(f, "{memory} of {size} bytes is larger than supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(err: io::Error) -> Self {
+        CartridgeError::Io(err)
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{CartridgeError, Mirror};
+
+const INES_MAGIC: [u8; 4] = *b"NES\x1A";
+
+/// The 16 byte header in front of every iNES and NES 2.0 ROM. NES 2.0 gives
+/// bytes 8-15 new meanings, [`CartridgeInfo`] sorts out which one applies.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub(crate) struct iNESHeader {
+    name: [u8; 4],
+    prog_rom_chunks: u8,
+    char_rom_chunks: u8,
+    mapper1: u8,
+    mapper2: u8,
+    prog_ram_size: u8,
+    tv_system1: u8,
+    tv_system2: u8,
+    /// Padding on iNES, RAM sizes, timing and system types on NES 2.0
+    extended: [u8; 5],
+}
+
+impl iNESHeader {
+    pub fn from_array(content: [u8; 16]) -> Self {
+        Self {
+            name: [content[0], content[1], content[2], content[3]],
+            prog_rom_chunks: content[4],
+            char_rom_chunks: content[5],
+            mapper1: content[6],
+            mapper2: content[7],
+            prog_ram_size: content[8],
+            tv_system1: content[9],
+            tv_system2: content[10],
+            extended: [
+                content[11],
+                content[12],
+                content[13],
+                content[14],
+                content[15],
+            ],
+        }
+    }
+
+    fn is_nes2(&self) -> bool {
+        self.mapper2 & 0x0C == 0x08
+    }
+
+    pub fn info(&self) -> Result<CartridgeInfo, CartridgeError> {
+        if self.name != INES_MAGIC {
+            return Err(CartridgeError::BadMagic(self.name));
+        }
+
+        let info = if self.is_nes2() {
+            self.nes2_info()?
+        } else {
+            self.ines_info()
+        };
+
+        if info.prog_rom_size == 0 {
+            return Err(CartridgeError::InconsistentHeader("no PRG-ROM"));
+        }
+
+        Ok(info)
+    }
+
+    fn ines_info(&self) -> CartridgeInfo {
+        // Headers dirtied by old tools ("DiskDude!") have junk in bytes 7-15,
+        // the upper mapper nibble can only be trusted when the padding is clean
+        let mapper_hi = if self.extended[1..].iter().all(|&byte| byte == 0) {
+            self.mapper2 & 0xF0
+        } else {
+            0
+        };
+
+        CartridgeInfo {
+            nes2: false,
+            mapper: (mapper_hi | self.mapper1 >> 4) as u16,
+            submapper: 0,
+            mirror: self.mirror(),
+            four_screen: self.mapper1 & 0x08 != 0,
+            battery: self.mapper1 & 0x02 != 0,
+            trainer: self.mapper1 & 0x04 != 0,
+
+            prog_rom_size: self.prog_rom_chunks as usize * 0x4000,
+            char_rom_size: self.char_rom_chunks as usize * 0x2000,
+            // A size of 0 means 8K for compatibility
+            prog_ram_size: self.prog_ram_size.max(1) as usize * 0x2000,
+            prog_nvram_size: 0,
+            char_ram_size: if self.char_rom_chunks == 0 { 0x2000 } else { 0 },
+            char_nvram_size: 0,
+
+            timing: if self.tv_system1 & 0x01 != 0 {
+                Timing::Pal
+            } else {
+                Timing::Ntsc
+            },
+            // Only NES 2.0 has extended console types, 3 means nothing here
+            console_type: match self.mapper2 & 0x03 {
+                1 => ConsoleType::VsSystem,
+                2 => ConsoleType::Playchoice10,
+                _ => ConsoleType::Nes,
+            },
+            vs_system: None,
+            misc_roms: 0,
+            expansion_device: 0,
+        }
+    }
+
+    fn nes2_info(&self) -> Result<CartridgeInfo, CartridgeError> {
+        let [char_ram, timing, system, misc_roms, expansion_device] = self.extended;
+
+        let prog_rom_size = nes2_rom_size(self.prog_rom_chunks, self.tv_system1 & 0x0F, 0x4000)
+            .ok_or(CartridgeError::InconsistentHeader("PRG-ROM size overflows"))?;
+        let char_rom_size = nes2_rom_size(self.char_rom_chunks, self.tv_system1 >> 4, 0x2000)
+            .ok_or(CartridgeError::InconsistentHeader("CHR-ROM size overflows"))?;
+
+        let console_type = ConsoleType::from_bits(self.mapper2 & 0x03, system & 0x0F);
+        let vs_system = (console_type == ConsoleType::VsSystem).then(|| VsSystem {
+            ppu: system & 0x0F,
+            hardware: VsHardware::from_bits(system >> 4),
+        });
+
+        Ok(CartridgeInfo {
+            nes2: true,
+            mapper: (self.prog_ram_size as u16 & 0x0F) << 8
+                | (self.mapper2 & 0xF0) as u16
+                | (self.mapper1 >> 4) as u16,
+            submapper: self.prog_ram_size >> 4,
+            mirror: self.mirror(),
+            four_screen: self.mapper1 & 0x08 != 0,
+            battery: self.mapper1 & 0x02 != 0,
+            trainer: self.mapper1 & 0x04 != 0,
+
+            prog_rom_size,
+            char_rom_size,
+            prog_ram_size: nes2_ram_size(self.tv_system2 & 0x0F),
+            prog_nvram_size: nes2_ram_size(self.tv_system2 >> 4),
+            char_ram_size: nes2_ram_size(char_ram & 0x0F),
+            char_nvram_size: nes2_ram_size(char_ram >> 4),
+
+            timing: match timing & 0x03 {
+                0 => Timing::Ntsc,
+                1 => Timing::Pal,
+                2 => Timing::MultiRegion,
+                _ => Timing::Dendy,
+            },
+            console_type,
+            vs_system,
+            misc_roms: misc_roms & 0x03,
+            expansion_device: expansion_device & 0x3F,
+        })
+    }
+
+    fn mirror(&self) -> Mirror {
+        if self.mapper1 & 0x01 > 0 {
+            Mirror::Vertical
+        } else {
+            Mirror::Horizontal
+        }
+    }
+}
+
+/// ROM size from the size LSB and its MSB nibble. An MSB of $F turns the LSB
+/// into an exponent and multiplier, `2^E * (MM * 2 + 1)` bytes.
+fn nes2_rom_size(lsb: u8, msb: u8, unit: usize) -> Option<usize> {
+    if msb == 0x0F {
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+        return 1usize
+            .checked_shl((lsb >> 2) as u32)?
+            .checked_mul(multiplier);
+    }
+
+    Some(((msb as usize) << 8 | lsb as usize) * unit)
+}
+
+/// RAM sizes are stored as a shift count, `64 << shift` bytes, 0 for none.
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+/// CPU/PPU timing the ROM was made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timing {
+    /// RP2C02, North America, Japan, South Korea and Taiwan
+    Ntsc,
+    /// RP2C07, Western Europe and Australia
+    Pal,
+    /// Runs on both, identifying the region by itself
+    MultiRegion,
+    /// UMC 6527P, the Dendy and other famiclones
+    Dendy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleType {
+    /// Famicom and NES
+    Nes,
+    VsSystem,
+    Playchoice10,
+    /// One of the extended console types from byte 13, e.g. a Famiclone with decimal mode
+    Extended(u8),
+}
+
+impl ConsoleType {
+    fn from_bits(console_type: u8, extended: u8) -> Self {
+        match console_type {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended(extended),
+        }
+    }
+}
+
+/// Arcade hardware a Vs. System ROM expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VsSystem {
+    /// Which RGB PPU the board has, see the NES 2.0 spec for the list
+    pub ppu: u8,
+    pub hardware: VsHardware,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VsHardware {
+    Unisystem,
+    /// Unisystems running games with protection hardware
+    RbiBaseball,
+    TkoBoxing,
+    SuperXevious,
+    IceClimberJapan,
+    DualSystem,
+    /// Dual System running Raid on Bungeling Bay
+    RaidOnBungelingBay,
+    Unknown(u8),
+}
+
+impl VsHardware {
+    fn from_bits(hardware: u8) -> Self {
+        match hardware {
+            0 => VsHardware::Unisystem,
+            1 => VsHardware::RbiBaseball,
+            2 => VsHardware::TkoBoxing,
+            3 => VsHardware::SuperXevious,
+            4 => VsHardware::IceClimberJapan,
+            5 => VsHardware::DualSystem,
+            6 => VsHardware::RaidOnBungelingBay,
+            _ => VsHardware::Unknown(hardware),
+        }
+    }
+}
+
+/// Everything the header says about a ROM. iNES headers leave out most of
+/// it, their fields get filled in with what iNES implies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CartridgeInfo {
+    /// The header is NES 2.0 rather than iNES
+    pub nes2: bool,
+    /// 12 bit on NES 2.0, 8 bit on iNES
+    pub mapper: u16,
+    /// Always 0 on iNES
+    pub submapper: u8,
+    /// Hardwired mirroring, boards with mirror control override it
+    pub mirror: Mirror,
+    pub four_screen: bool,
+    pub battery: bool,
+    /// A 512 byte trainer sits between the header and the PRG-ROM
+    pub trainer: bool,
+
+    /// Sizes in bytes
+    pub prog_rom_size: usize,
+    pub char_rom_size: usize,
+    pub prog_ram_size: usize,
+    /// Battery backed PRG-RAM
+    pub prog_nvram_size: usize,
+    pub char_ram_size: usize,
+    /// Battery backed CHR-RAM
+    pub char_nvram_size: usize,
+
+    pub timing: Timing,
+    pub console_type: ConsoleType,
+    /// `Some` for Vs. System ROMs on NES 2.0
+    pub vs_system: Option<VsSystem>,
+    /// Number of ROMs after the CHR-ROM, e.g. the PlayChoice-10 INST-ROM
+    pub misc_roms: u8,
+    /// Default expansion device, numbered as in the NES 2.0 spec, 0 for unspecified
+    pub expansion_device: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A NES 2.0 header with one 16K PRG bank and the given bytes changed.
+    fn header(bytes: &[(usize, u8)]) -> [u8; 16] {
+        let mut header = [0x00; 16];
+        header[..4].copy_from_slice(&INES_MAGIC);
+        header[4] = 0x01;
+        header[7] = 0x08;
+
+        for &(index, byte) in bytes {
+            header[index] = byte;
+        }
+
+        header
+    }
+
+    fn info(bytes: &[(usize, u8)]) -> CartridgeInfo {
+        iNESHeader::from_array(header(bytes)).info().unwrap()
+    }
+
+    #[test]
+    fn rom_sizes() {
+        // (LSB, MSB nibble, unit, size)
+        let cases = [
+            (0x02, 0x0, 0x4000, Some(0x8000)),
+            (0x00, 0x1, 0x4000, Some(0x400000)),
+            (0x10, 0x0, 0x2000, Some(0x20000)),
+            // 2^14 * 1
+            (14 << 2, 0xF, 0x4000, Some(0x4000)),
+            // 2^10 * 3
+            (10 << 2 | 0x01, 0xF, 0x4000, Some(3 * 1024)),
+            // 2^3 * 7
+            (3 << 2 | 0x03, 0xF, 0x2000, Some(56)),
+            (63 << 2 | 0x03, 0xF, 0x4000, None),
+        ];
+
+        for (lsb, msb, unit, size) in cases {
+            assert_eq!(nes2_rom_size(lsb, msb, unit), size, "{lsb:#04X} {msb:#X}");
+        }
+    }
+
+    #[test]
+    fn ram_sizes() {
+        let cases = [(0, 0), (1, 128), (7, 0x2000), (9, 0x8000), (15, 0x200000)];
+
+        for (shift, size) in cases {
+            assert_eq!(nes2_ram_size(shift), size, "shift {shift}");
+        }
+
+        let info = info(&[(10, 0x97), (11, 0x07)]);
+        assert_eq!(info.prog_ram_size, 0x2000);
+        assert_eq!(info.prog_nvram_size, 0x8000);
+        assert_eq!(info.char_ram_size, 0x2000);
+        assert_eq!(info.char_nvram_size, 0);
+    }
+
+    #[test]
+    fn mapper_and_submapper() {
+        let cases = [
+            ([0x00, 0x08, 0x00], 0, 0),
+            ([0x10, 0x08, 0x00], 1, 0),
+            ([0x50, 0xA8, 0x10], 0xA5, 1),
+            ([0x50, 0x58, 0x30], 0x55, 3),
+            ([0xF0, 0xF8, 0xFF], 0xFFF, 0xF),
+            ([0x20, 0x18, 0x52], 0x212, 5),
+        ];
+
+        for ([flags6, flags7, byte8], mapper, submapper) in cases {
+            let info = info(&[(6, flags6), (7, flags7), (8, byte8)]);
+            assert!(info.nes2);
+            assert_eq!((info.mapper, info.submapper), (mapper, submapper));
+        }
+    }
+
+    #[test]
+    fn timing() {
+        let cases = [
+            (0x00, Timing::Ntsc),
+            (0x01, Timing::Pal),
+            (0x02, Timing::MultiRegion),
+            (0x03, Timing::Dendy),
+            (0xFD, Timing::Pal),
+        ];
+
+        for (byte, timing) in cases {
+            assert_eq!(info(&[(12, byte)]).timing, timing);
+        }
+    }
+
+    #[test]
+    fn console_types() {
+        let info_vs = info(&[(7, 0x09), (13, 0x53)]);
+        assert_eq!(info_vs.console_type, ConsoleType::VsSystem);
+        assert_eq!(
+            info_vs.vs_system,
+            Some(VsSystem {
+                ppu: 3,
+                hardware: VsHardware::DualSystem,
+            })
+        );
+
+        let extended = info(&[(7, 0x0B), (13, 0x03)]);
+        assert_eq!(extended.console_type, ConsoleType::Extended(3));
+        assert_eq!(extended.vs_system, None);
+
+        // iNES 1.0 has no extended console types
+        let ines = info(&[(7, 0x03)]);
+        assert!(!ines.nes2);
+        assert_eq!(ines.console_type, ConsoleType::Nes);
+    }
+
+    #[test]
+    fn ines_defaults() {
+        let info = info(&[(5, 0x00), (7, 0x00)]);
+
+        assert!(!info.nes2);
+        assert_eq!(info.submapper, 0);
+        assert_eq!(info.prog_rom_size, 0x4000);
+        assert_eq!(info.prog_ram_size, 0x2000);
+        assert_eq!(info.char_ram_size, 0x2000);
+        assert_eq!(info.timing, Timing::Ntsc);
+    }
+}
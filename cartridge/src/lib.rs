@@ -1,159 +1,461 @@
-use std::{
-    fs::File,
-    io::{Read, Seek},
-};
+use std::{fs::File, io::Read};
 
 use mappers::prelude::*;
+pub use mappers::{MappedPpuAddress, MapperRegistry, Mirror};
 
-#[derive(Clone, Debug, PartialEq)]
-pub enum Mirror {
-    Horizontal,
-    Vertical,
-    OnescreenLo,
-    OnescreenHi,
-}
+mod error;
+mod header;
+pub use error::{CartridgeError, RomSection};
+pub use header::{CartridgeInfo, ConsoleType, Timing, VsHardware, VsSystem};
 
-#[allow(non_camel_case_types, dead_code)]
-#[derive(Debug)]
-struct iNESHeader {
-    name: [u8; 4],
-    prog_rom_chunks: u8,
-    char_rom_chunks: u8,
-    mapper1: u8,
-    mapper2: u8,
-    prog_ram_size: u8,
-    tv_system1: u8,
-    tv_system2: u8,
-    unused: [u8; 5],
-}
+use header::iNESHeader;
 
-impl iNESHeader {
-    pub fn from_array(content: [u8; 16]) -> Self {
-        Self {
-            name: [content[0], content[1], content[2], content[3]],
-            prog_rom_chunks: content[4],
-            char_rom_chunks: content[5],
-            mapper1: content[6],
-            mapper2: content[7],
-            prog_ram_size: content[8],
-            tv_system1: content[9],
-            tv_system2: content[10],
-            unused: [
-                content[11],
-                content[12],
-                content[13],
-                content[14],
-                content[15],
-            ],
-        }
+/// Reads `len` bytes of `section`, a short file is an error rather than a short read.
+fn read_section(
+    reader: &mut impl Read,
+    section: RomSection,
+    len: usize,
+) -> Result<Vec<u8>, CartridgeError> {
+    let mut data = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut data)?;
+
+    if data.len() < len {
+        return Err(CartridgeError::Truncated {
+            section,
+            expected: len,
+            found: data.len(),
+        });
     }
+
+    Ok(data)
 }
 
 #[derive(Debug)]
 pub struct Cartridge {
     pub mapper: Box<dyn Mapper>,
 
-    pub mapper_id: u8,
+    pub mapper_id: u16,
     pub prog_banks: u8,
     pub char_banks: u8,
 
     pub prog_mem: Vec<u8>,
     pub char_mem: Vec<u8>,
+    pub prog_ram: Vec<u8>,
 
     pub mirror: Mirror,
+    /// PRG-RAM survives power off, the mapper's `battery_ram` always does
+    pub battery: bool,
+    pub info: CartridgeInfo,
 }
 
 impl Cartridge {
-    #[allow(clippy::new_ret_no_self)]
-    pub fn new(file_name: String) -> Self {
-        let mut file = File::open(file_name).unwrap();
+    pub fn new(file_name: String) -> Result<Self, CartridgeError> {
+        Self::with_registry(file_name, &MapperRegistry::default())
+    }
+
+    /// Like [`Cartridge::new`], but looks the mapper up in `registry`, for boards
+    /// that aren't built in.
+    pub fn with_registry(
+        file_name: String,
+        registry: &MapperRegistry,
+    ) -> Result<Self, CartridgeError> {
+        Self::from_reader_with_registry(File::open(file_name)?, registry)
+    }
+
+    /// Loads a ROM image already in memory.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        Self::from_reader(bytes)
+    }
+
+    /// Loads a ROM image from anything readable, only reading as far as the header says.
+    pub fn from_reader(reader: impl Read) -> Result<Self, CartridgeError> {
+        Self::from_reader_with_registry(reader, &MapperRegistry::default())
+    }
 
-        let mut header_raw: [u8; 16] = [0; 16];
-        file.read_exact(&mut header_raw).unwrap();
+    pub fn from_reader_with_registry(
+        mut reader: impl Read,
+        registry: &MapperRegistry,
+    ) -> Result<Self, CartridgeError> {
+        let header_raw: [u8; 16] = read_section(&mut reader, RomSection::Header, 16)?
+            .try_into()
+            .unwrap();
 
-        let header = iNESHeader::from_array(header_raw);
-        println!("{:#?}", header);
+        let info = iNESHeader::from_array(header_raw).info()?;
 
         // ignore trainer
-        if header.mapper1 & 0x04 > 0 {
-            println!("Ignoring trainer");
-            file.seek(std::io::SeekFrom::Current(512)).unwrap();
+        if info.trainer {
+            read_section(&mut reader, RomSection::Trainer, 512)?;
         }
 
-        let mapper_id = ((header.mapper2 >> 4) << 4) | (header.mapper1 >> 4);
-        let mirror = if header.mapper1 & 0x01 > 0 {
-            Mirror::Vertical
-        } else {
-            Mirror::Horizontal
-        };
-        println!(
-            "Mapper ID: {mapper_id}\nMirror: {}",
-            if mirror == Mirror::Vertical {
-                "Vertical"
-            } else {
-                "Horizontal"
+        // NES 2.0 sizes don't have to be whole banks, the mappers want them padded
+        let prog_banks = u8::try_from(info.prog_rom_size.div_ceil(0x4000)).map_err(|_| {
+            CartridgeError::TooLarge {
+                memory: "PRG-ROM",
+                size: info.prog_rom_size,
             }
-        );
+        })?;
+        let char_banks = u8::try_from(info.char_rom_size.div_ceil(0x2000)).map_err(|_| {
+            CartridgeError::TooLarge {
+                memory: "CHR-ROM",
+                size: info.char_rom_size,
+            }
+        })?;
+
+        let mut prog_mem = read_section(&mut reader, RomSection::Prg, info.prog_rom_size)?;
+        prog_mem.resize(prog_banks as usize * 0x4000, 0);
 
-        let prog_banks = header.prog_rom_chunks;
-        let char_banks = header.char_rom_chunks;
-        println!("Program Banks: {prog_banks}\nChar Banks: {char_banks}");
+        let mut char_mem = read_section(&mut reader, RomSection::Chr, info.char_rom_size)?;
+        char_mem.resize(char_banks as usize * 0x2000, 0);
 
-        let mut prog_mem: Vec<u8> = vec![0; (prog_banks as usize) * 0x4000];
-        file.read_exact(&mut prog_mem).unwrap();
+        // Boards with CHR-RAM get at least a single 8K bank
+        if char_banks == 0 {
+            char_mem = vec![0; (info.char_ram_size + info.char_nvram_size).max(0x2000)];
+        }
 
-        let mut char_mem: Vec<u8> = vec![0; (char_banks as usize) * 0x2000];
-        file.read_exact(&mut char_mem).unwrap();
+        let prog_ram_size = info.prog_ram_size + info.prog_nvram_size;
+        let prog_ram_banks = u8::try_from(prog_ram_size.div_ceil(0x2000).max(1)).map_err(|_| {
+            CartridgeError::TooLarge {
+                memory: "PRG-RAM",
+                size: prog_ram_size,
+            }
+        })?;
+        let prog_ram: Vec<u8> = vec![0; (prog_ram_banks as usize) * 0x2000];
 
-        let mapper = match mapper_id {
-            0 => Box::new(INES_000::new(prog_banks, char_banks)),
-            _ => todo!(),
+        let config = MapperConfig {
+            mapper: info.mapper,
+            submapper: info.submapper,
+            prog_banks,
+            char_banks,
+            prog_ram_banks,
+            battery: info.battery,
         };
+        let mapper = registry
+            .create(&config)
+            .ok_or(CartridgeError::UnsupportedMapper {
+                mapper: info.mapper,
+                submapper: info.submapper,
+            })?;
 
-        Self {
+        Ok(Self {
             mapper,
-            mapper_id,
+            mapper_id: info.mapper,
             prog_banks,
             char_banks,
             prog_mem,
             char_mem,
-            mirror,
-        }
+            prog_ram,
+            mirror: info.mirror.clone(),
+            battery: info.battery,
+            info,
+        })
     }
 
-    pub fn cpu_read(&self, address: u16) -> Option<u8> {
-        if let Some(mapped_addr) = self.mapper.cpu_read(address) {
-            return Some(self.prog_mem[mapped_addr as usize]);
+    /// Current nametable mirroring, the mapper can override the header's.
+    pub fn mirror(&self) -> Mirror {
+        self.mapper.mirror().unwrap_or_else(|| self.mirror.clone())
+    }
+
+    /// Memory to persist between runs: the PRG-RAM if the header has the battery bit, followed
+    /// by whatever the mapper keeps in `battery_ram`. `None` when there's neither.
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        let ram = self.mapper.battery_ram();
+        if !self.battery && ram.is_none() {
+            return None;
+        }
+
+        let mut data = if self.battery {
+            self.prog_ram.clone()
+        } else {
+            Vec::new()
+        };
+        if let Some(ram) = ram {
+            data.extend_from_slice(ram);
         }
 
-        None
+        Some(data)
     }
 
-    pub fn cpu_write(&mut self, address: u16, data: u8) -> Option<()> {
-        if let Some(mapped_addr) = self.mapper.cpu_write(address) {
-            self.prog_mem[mapped_addr as usize] = data;
+    /// Restores memory returned by [`Cartridge::save_data`], a size mismatch loads what fits.
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        let rest = if self.battery {
+            let (prog_ram, rest) = data.split_at(data.len().min(self.prog_ram.len()));
+            self.prog_ram[..prog_ram.len()].copy_from_slice(prog_ram);
+            rest
+        } else {
+            data
+        };
 
-            return Some(());
+        if let Some(ram) = self.mapper.battery_ram_mut() {
+            let len = rest.len().min(ram.len());
+            ram[..len].copy_from_slice(&rest[..len]);
         }
+    }
+
+    pub fn cpu_clock(&mut self) {
+        self.mapper.cpu_clock();
+    }
+
+    /// Lets the mapper see an address the PPU put on the bus.
+    pub fn ppu_address(&mut self, address: u16) {
+        self.mapper.ppu_address(address);
+    }
 
-        None
+    pub fn irq(&self) -> bool {
+        self.mapper.irq()
     }
 
-    pub fn ppu_read(&self, address: u16) -> Option<u8> {
-        if let Some(mapped_addr) = self.mapper.ppu_read(address) {
-            return Some(self.char_mem[mapped_addr as usize]);
+    pub fn audio_output(&self) -> f32 {
+        self.mapper.audio_output()
+    }
+
+    pub fn cpu_read(&mut self, address: u16) -> Option<u8> {
+        match self.mapper.cpu_read(address)? {
+            MappedAddress::Prog(mapped_addr) => Some(self.prog_mem[mapped_addr]),
+            MappedAddress::Ram(mapped_addr) => Some(self.prog_ram[mapped_addr]),
+            MappedAddress::Data(data) => Some(data),
+        }
+    }
+
+    pub fn cpu_write(&mut self, address: u16, mut data: u8) -> Option<()> {
+        if address >= 0x8000 && self.mapper.bus_conflicts() {
+            data &= self.cpu_read(address).unwrap_or(0xFF);
         }
 
-        None
+        match self.mapper.cpu_write(address, data)? {
+            MappedAddress::Prog(mapped_addr) => self.prog_mem[mapped_addr] = data,
+            MappedAddress::Ram(mapped_addr) => self.prog_ram[mapped_addr] = data,
+            MappedAddress::Data(_) => {}
+        }
+
+        Some(())
+    }
+
+    /// Pattern table and nametable access ($0000-$2FFF), `None` means the PPU handles it,
+    /// following `mirror()` for nametables. CHR gets resolved here, so the PPU only ever sees
+    /// CIRAM or data.
+    pub fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        match self.mapper.ppu_read(address)? {
+            MappedPpuAddress::Chr(mapped_addr) => {
+                Some(MappedPpuAddress::Data(self.char_mem[mapped_addr]))
+            }
+            mapped => Some(mapped),
+        }
     }
 
-    pub fn ppu_write(&mut self, address: u16, data: u8) -> Option<()> {
-        if let Some(mapped_addr) = self.mapper.ppu_write(address) {
-            self.char_mem[mapped_addr as usize] = data;
+    pub fn ppu_write(&mut self, address: u16, data: u8) -> Option<MappedPpuAddress> {
+        match self.mapper.ppu_write(address, data)? {
+            MappedPpuAddress::Chr(mapped_addr) => {
+                // Only CHR-RAM takes the write
+                if self.char_banks == 0 {
+                    self.char_mem[mapped_addr] = data;
+                }
 
-            return Some(());
+                Some(MappedPpuAddress::Data(data))
+            }
+            mapped => Some(mapped),
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An iNES image with the given mapper, sizes in 16K/8K banks and `prog_len`/`char_len`
+    /// bytes actually following the header.
+    fn image(
+        mapper: u8,
+        prog_banks: u8,
+        char_banks: u8,
+        prog_len: usize,
+        char_len: usize,
+    ) -> Vec<u8> {
+        let mut image = vec![
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            prog_banks,
+            char_banks,
+            mapper << 4,
+            mapper & 0xF0,
+        ];
+        image.resize(16, 0x00);
+        image.extend((0..prog_len).map(|i| i as u8));
+        image.extend((0..char_len).map(|i| (i >> 8) as u8));
+        image
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut rom = image(0, 1, 1, 0x4000, 0x2000);
+        rom[3] = 0x00;
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::BadMagic([b'N', b'E', b'S', 0x00]))
+        ));
+    }
+
+    #[test]
+    fn truncated_prg() {
+        let rom = image(0, 2, 1, 0x5000, 0);
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::Truncated {
+                section: RomSection::Prg,
+                expected: 0x8000,
+                found: 0x5000,
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_chr() {
+        let rom = image(0, 1, 1, 0x4000, 0x1000);
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::Truncated {
+                section: RomSection::Chr,
+                expected: 0x2000,
+                found: 0x1000,
+            })
+        ));
+    }
+
+    #[test]
+    fn unsupported_mapper() {
+        let rom = image(0xFF, 1, 1, 0x4000, 0x2000);
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::UnsupportedMapper {
+                mapper: 0xFF,
+                submapper: 0,
+            })
+        ));
+    }
+
+    #[test]
+    fn from_bytes_nrom() {
+        let mut rom = image(0, 1, 1, 0x4000, 0x2000);
+        rom[6] |= 0x01;
+
+        let mut cart = Cartridge::from_bytes(&rom).unwrap();
+        assert_eq!(cart.mapper_id, 0);
+        assert_eq!(cart.mirror(), Mirror::Vertical);
+        assert_eq!(cart.prog_mem, rom[16..16 + 0x4000]);
+        assert_eq!(cart.char_mem, rom[16 + 0x4000..]);
+
+        // NROM-128 shows up at both $8000 and $C000
+        assert_eq!(cart.cpu_read(0x8123), Some(0x23));
+        assert_eq!(cart.cpu_read(0xC123), Some(0x23));
+        assert_eq!(cart.ppu_read(0x1234), Some(MappedPpuAddress::Data(0x12)));
+    }
+
+    #[test]
+    fn from_reader_skips_trainer() {
+        let plain = image(0, 1, 1, 0x4000, 0x2000);
+        let mut rom = plain[..16].to_vec();
+        rom[6] |= 0x04;
+        rom.extend([0xEE; 512]);
+        rom.extend(&plain[16..]);
+
+        let cart = Cartridge::from_reader(rom.as_slice()).unwrap();
+        assert_eq!(cart.prog_mem, plain[16..16 + 0x4000]);
+    }
+
+    #[test]
+    fn truncated_header() {
+        let rom = &image(0, 1, 1, 0x4000, 0x2000)[..10];
+
+        assert!(matches!(
+            Cartridge::from_bytes(rom),
+            Err(CartridgeError::Truncated {
+                section: RomSection::Header,
+                expected: 16,
+                found: 10,
+            })
+        ));
+    }
+
+    #[test]
+    fn truncated_trainer() {
+        let mut rom = image(0, 1, 1, 0, 0);
+        rom[6] |= 0x04;
+        rom.extend([0xEE; 100]);
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::Truncated {
+                section: RomSection::Trainer,
+                expected: 512,
+                found: 100,
+            })
+        ));
+    }
+
+    #[test]
+    fn nina_001_with_chr_ram() {
+        // NES 2.0, mapper 34 submapper 1 and no CHR-ROM
+        let mut rom = image(34, 2, 0, 0x8000, 0);
+        rom[7] |= 0x08;
+        rom[8] = 0x10;
+
+        let mut cart = Cartridge::from_bytes(&rom).unwrap();
+        assert_eq!(cart.cpu_write(0x7FFF, 0x05), Some(()));
+        assert_eq!(
+            cart.ppu_write(0x1234, 0xAB),
+            Some(MappedPpuAddress::Data(0xAB))
+        );
+        assert_eq!(cart.ppu_read(0x1234), Some(MappedPpuAddress::Data(0xAB)));
+    }
+
+    #[test]
+    fn eeprom_saved_without_battery() {
+        // Mapper 159 with a 24C01, the battery bit left clear like many dumps
+        let rom = image(159, 2, 1, 0x8000, 0x2000);
+
+        let mut cart = Cartridge::from_bytes(&rom).unwrap();
+        assert!(!cart.battery);
+
+        let mut save = cart.save_data().unwrap();
+        assert_eq!(save.len(), 128);
+
+        save[5] = 0x42;
+        cart.load_save_data(&save);
+        assert_eq!(cart.save_data().unwrap()[5], 0x42);
+
+        // Plain boards without a battery still have nothing to save
+        let rom = image(0, 1, 1, 0x4000, 0x2000);
+        assert!(Cartridge::from_bytes(&rom).unwrap().save_data().is_none());
+    }
+
+    #[test]
+    fn too_many_prg_banks() {
+        // NES 2.0 with the PRG-ROM size MSB set, 256 banks
+        let mut rom = image(0, 0, 1, 0, 0);
+        rom[7] |= 0x08;
+        rom[9] = 0x01;
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::TooLarge {
+                memory: "PRG-ROM",
+                size: 0x400000,
+            })
+        ));
+    }
+
+    #[test]
+    fn no_prg_rom() {
+        let rom = image(0, 0, 1, 0, 0x2000);
 
-        None
+        assert!(matches!(
+            Cartridge::from_bytes(&rom),
+            Err(CartridgeError::InconsistentHeader(_))
+        ));
     }
 }
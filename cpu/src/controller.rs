@@ -0,0 +1,65 @@
+use bitflags::bitflags;
+
+bitflags! {
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct Buttons: u8 {
+        const A = 0b0000_0001;
+        const B = 0b0000_0010;
+        const SELECT = 0b0000_0100;
+        const START = 0b0000_1000;
+        const UP = 0b0001_0000;
+        const DOWN = 0b0010_0000;
+        const LEFT = 0b0100_0000;
+        const RIGHT = 0b1000_0000;
+    }
+}
+
+/// Device plugged into one of the controller ports at $4016/$4017.
+pub trait Controller: std::fmt::Debug {
+    /// Called on every write to $4016, only bit 0 (the strobe line) matters.
+    fn strobe(&mut self, strobe: bool);
+
+    /// Returns the serial data bits of the next read, the upper bits are open bus.
+    fn read(&mut self) -> u8;
+
+    fn set_buttons(&mut self, buttons: Buttons);
+}
+
+#[derive(Debug, Default)]
+pub struct StandardController {
+    buttons: Buttons,
+    shift: u8,
+    strobe: bool,
+}
+
+impl Controller for StandardController {
+    fn strobe(&mut self, strobe: bool) {
+        self.strobe = strobe;
+
+        if strobe {
+            self.shift = self.buttons.bits();
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        // While strobe is high the shift register keeps reloading, so A is all we see
+        if self.strobe {
+            return self.buttons.bits() & 0x01;
+        }
+
+        let data = self.shift & 0x01;
+
+        // An official controller returns 1s once all 8 buttons have been shifted out
+        self.shift = (self.shift >> 1) | 0x80;
+
+        data
+    }
+
+    fn set_buttons(&mut self, buttons: Buttons) {
+        self.buttons = buttons;
+
+        if self.strobe {
+            self.shift = buttons.bits();
+        }
+    }
+}
@@ -0,0 +1,60 @@
+/// OAM DMA unit triggered by writes to $4014.
+///
+/// Copies a full 256 byte page into the PPU's OAM, alternating a read cycle
+/// with a write cycle. The CPU is suspended for the whole transfer, plus one
+/// halt cycle and one more alignment cycle when the transfer starts on an odd
+/// CPU cycle, giving the 513/514 cycle stall of the real hardware.
+#[derive(Debug, Default)]
+pub struct OamDma {
+    page: u8,
+    addr: u8,
+    data: u8,
+    transfer: bool,
+    dummy: bool,
+}
+
+pub enum DmaCycle {
+    Idle,
+    Read(u16),
+    Write(u8),
+}
+
+impl OamDma {
+    pub fn start(&mut self, page: u8) {
+        self.page = page;
+        self.addr = 0x00;
+        self.transfer = true;
+        self.dummy = true;
+    }
+
+    pub fn active(&self) -> bool {
+        self.transfer
+    }
+
+    /// Runs one stalled CPU cycle and returns the bus access it performs.
+    pub fn cycle(&mut self, odd_cycle: bool) -> DmaCycle {
+        if self.dummy {
+            // Wait for a get cycle to line up before reading
+            if odd_cycle {
+                self.dummy = false;
+            }
+
+            return DmaCycle::Idle;
+        }
+
+        if !odd_cycle {
+            return DmaCycle::Read(((self.page as u16) << 8) | self.addr as u16);
+        }
+
+        self.addr = self.addr.wrapping_add(1);
+        if self.addr == 0x00 {
+            self.transfer = false;
+        }
+
+        DmaCycle::Write(self.data)
+    }
+
+    pub fn set_data(&mut self, data: u8) {
+        self.data = data;
+    }
+}
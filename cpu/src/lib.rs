@@ -1,6 +1,10 @@
+mod controller;
+mod dma;
 mod memory;
 mod nes;
+pub use controller::{Buttons, Controller, StandardController};
 pub use nes::NES;
+pub use nestest::NESTEST_ROM;
 pub use ppu::PAL_PALETTE;
 
 mod nestest;
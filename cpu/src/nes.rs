@@ -3,27 +3,50 @@ use std::rc::Rc;
 
 use emulator_6502::*;
 
+pub use apu::APU;
 use cartridge::Cartridge;
 pub use ppu::PPU;
 
+use crate::controller::{Buttons, Controller, StandardController};
+use crate::dma::{DmaCycle, OamDma};
+
 #[allow(clippy::upper_case_acronyms, dead_code)]
 #[derive(Debug)]
 pub struct NES {
     cpu: Rc<RefCell<MOS6502>>,
     memory: Vec<u8>,
     pub ppu: PPU,
+    pub apu: APU,
     cartridge: Option<Rc<RefCell<Cartridge>>>,
+    dma: OamDma,
+    dmc_stall: u8,
+    controllers: [Box<dyn Controller>; 2],
     ticks: u32,
+    cpu_cycles: u64,
 }
 
 impl Interface6502 for NES {
     fn read(&mut self, address: u16) -> u8 {
-        if let Some(data) = self.cartridge.as_ref().unwrap().borrow().cpu_read(address) {
+        // Bound first so the cartridge isn't still borrowed while the PPU reaches into it
+        let cart_data = self
+            .cartridge
+            .as_ref()
+            .unwrap()
+            .borrow_mut()
+            .cpu_read(address);
+
+        if let Some(data) = cart_data {
             return data;
         } else if (0x0000..=0x1FFF).contains(&address) {
             return self.memory[(address & 0x07FF) as usize];
         } else if (0x2000..=0x3FFF).contains(&address) {
             return self.ppu.cpu_read(address & 0x0007);
+        } else if address == 0x4015 {
+            return self.apu.cpu_read(address);
+        } else if (0x4016..=0x4017).contains(&address) {
+            // Only the low bits are driven, the rest is left over from the address high byte
+            let data = self.controllers[(address & 0x0001) as usize].read();
+            return (data & 0x1F) | ((address >> 8) as u8 & 0xE0);
         }
 
         0x00
@@ -42,6 +65,14 @@ impl Interface6502 for NES {
             self.memory[(address & 0x07FF) as usize] = data;
         } else if (0x2000..=0x3FFF).contains(&address) {
             self.ppu.cpu_write(address & 0x0007, data);
+        } else if (0x4000..=0x4013).contains(&address) || address == 0x4015 || address == 0x4017 {
+            self.apu.cpu_write(address, data);
+        } else if address == 0x4014 {
+            self.dma.start(data);
+        } else if address == 0x4016 {
+            for controller in self.controllers.iter_mut() {
+                controller.strobe(data & 0x01 != 0);
+            }
         }
     }
 }
@@ -52,8 +83,16 @@ impl Default for NES {
             cpu: Rc::default(),
             memory: vec![0; (1024 * 2 + 1) as usize],
             ppu: PPU::default(),
+            apu: APU::default(),
             cartridge: Option::default(),
+            dma: OamDma::default(),
+            dmc_stall: 0,
+            controllers: [
+                Box::new(StandardController::default()),
+                Box::new(StandardController::default()),
+            ],
             ticks: u32::default(),
+            cpu_cycles: u64::default(),
         }
     }
 }
@@ -86,6 +125,22 @@ impl NES {
         pc
     }
 
+    /// Plugs a device into controller port 0 (player 1) or 1 (player 2).
+    pub fn attach_controller(&mut self, port: usize, controller: Box<dyn Controller>) {
+        self.controllers[port] = controller;
+    }
+
+    /// Sets the buttons currently held for port 0 (player 1) or 1 (player 2).
+    pub fn set_buttons(&mut self, port: usize, buttons: Buttons) {
+        self.controllers[port].set_buttons(buttons);
+    }
+
+    /// Moves the audio produced so far into `out` as mono samples at the APU's sample rate,
+    /// returning how many were written.
+    pub fn drain_audio(&mut self, out: &mut [f32]) -> usize {
+        self.apu.drain_audio(out)
+    }
+
     pub fn attach_cart(&mut self, cart: Rc<RefCell<Cartridge>>) {
         self.cartridge = Some(cart.clone());
         self.ppu.attach_cart(cart);
@@ -94,15 +149,58 @@ impl NES {
     pub fn tick(&mut self) {
         self.ppu.tick();
 
-        if self.ticks % 3 == 0 {
+        if self.ticks.is_multiple_of(3) {
             let cpu = Rc::clone(&self.cpu);
-            cpu.borrow_mut().cycle(self);
+
+            if self.dmc_stall > 0 {
+                self.dmc_stall -= 1;
+            } else if self.dma.active() {
+                // The CPU is suspended while the DMA unit owns the bus
+                match self.dma.cycle(self.cpu_cycles % 2 == 1) {
+                    DmaCycle::Read(address) => {
+                        let data = self.read(address);
+                        self.dma.set_data(data);
+                    }
+                    DmaCycle::Write(data) => self.ppu.cpu_write(0x0004, data),
+                    DmaCycle::Idle => {}
+                }
+            } else {
+                cpu.borrow_mut().cycle(self);
+            }
+
+            let mut irq = false;
+
+            if let Some(cart) = self.cartridge.as_ref() {
+                cart.borrow_mut().cpu_clock();
+                irq |= cart.borrow().irq();
+                self.apu.set_expansion_output(cart.borrow().audio_output());
+            }
+
+            self.apu.tick();
+
+            if let Some(address) = self.apu.dmc_fetch_address() {
+                let data = self.read(address);
+                self.apu.dmc_load(data);
+
+                // The DMC halts the CPU for its fetch, an OAM DMA already holds it for part of that
+                self.dmc_stall += if self.dma.active() { 2 } else { 4 };
+            }
+
+            irq |= self.apu.irq();
+
+            // The IRQ line is a level, only latch it when the CPU is about to take it so one that
+            // gets acknowledged inside the handler doesn't fire a second time after RTI
+            let interrupt_disable = cpu.borrow().get_status_register() & 0x04 != 0;
+            if irq && !interrupt_disable && cpu.borrow().get_remaining_cycles() == 0 {
+                cpu.borrow_mut().interrupt_request();
+            }
 
             if self.ppu.nmi {
                 self.ppu.nmi = false;
                 cpu.borrow_mut().non_maskable_interrupt_request();
             }
 
+            self.cpu_cycles += 1;
             self.ticks = 0;
         }
 
@@ -1,18 +1,115 @@
 mod plane0;
+mod registry;
 
+pub use registry::{MapperConfig, MapperConstructor, MapperFeatures, MapperInfo, MapperRegistry};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mirror {
+    Horizontal,
+    Vertical,
+    OnescreenLo,
+    OnescreenHi,
+}
+
+/// Where a CPU access ends up on the cartridge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MappedAddress {
+    /// Offset into the PRG-ROM
+    Prog(usize),
+    /// Offset into the PRG-RAM at $6000-$7FFF
+    Ram(usize),
+    /// A value the mapper drives on the bus itself, e.g. a register or its internal RAM
+    Data(u8),
+}
+
+/// Where a PPU access to the pattern tables or nametables ($0000-$2FFF) ends up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MappedPpuAddress {
+    /// Offset into the CHR memory, writes only land on CHR-RAM
+    Chr(usize),
+    /// One of the two 1K pages of CIRAM inside the console
+    Ciram(usize),
+    /// The mapper answers the access itself, with the byte read for reads
+    Data(u8),
+}
+
+/// A cartridge board. Mappers own their registers and get to see every CPU cycle and every
+/// address the PPU puts on the bus, so anything on the board can be modelled here without
+/// help from `Cartridge` or the PPU.
 pub trait Mapper: std::fmt::Debug {
-    fn get_prog_banks(&self) -> u8;
-    fn get_char_banks(&self) -> u8;
+    /// CPU access to $4020-$FFFF, `None` leaves the bus open.
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress>;
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress>;
+
+    /// PPU access to $0000-$2FFF. A nametable access the mapper returns `None` for follows
+    /// `mirror()`.
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress>;
+
+    /// Goes wherever a read of the same address would, for boards that treat writes the same.
+    fn ppu_write(&mut self, address: u16, _data: u8) -> Option<MappedPpuAddress> {
+        self.ppu_read(address)
+    }
+
+    /// Mirroring set by the mapper at runtime, `None` keeps the one from the header.
+    fn mirror(&self) -> Option<Mirror> {
+        None
+    }
+
+    /// Called once every CPU cycle.
+    fn cpu_clock(&mut self) {}
+
+    /// Called with every address the PPU puts on the cartridge bus ($0000-$3EFF), pattern
+    /// fetches included, right before the access itself.
+    fn ppu_address(&mut self, _address: u16) {}
+
+    /// Boards without a chip select on the PRG-ROM have it drive the bus while a register is
+    /// written, so the value written gets ANDed with the ROM byte at that address.
+    fn bus_conflicts(&self) -> bool {
+        false
+    }
+
+    /// Level of the cartridge IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Save memory inside the mapper, battery backed RAM or an EEPROM. It gets saved even
+    /// when the header has no battery bit, which a lot of EEPROM board dumps lack.
+    fn battery_ram(&self) -> Option<&[u8]> {
+        None
+    }
 
-    fn cpu_read(&self, address: u16) -> Option<u16>;
-    fn cpu_write(&self, address: u16) -> Option<u16>;
+    fn battery_ram_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
 
-    fn ppu_read(&self, address: u16) -> Option<u16>;
-    fn ppu_write(&self, address: u16) -> Option<u16>;
+    /// Expansion audio output, on the scale of the APU mix where a 2A03 pulse channel at full
+    /// volume is about 0.15. Gets added to the APU output every CPU cycle.
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
 }
 
 pub mod prelude {
-    pub use crate::Mapper;
+    pub use crate::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+    pub use crate::{MapperConfig, MapperFeatures, MapperInfo, MapperRegistry};
 
     pub use crate::plane0::ines_000::INES_000;
+    pub use crate::plane0::ines_001::INES_001;
+    pub use crate::plane0::ines_002::INES_002;
+    pub use crate::plane0::ines_003::INES_003;
+    pub use crate::plane0::ines_004::INES_004;
+    pub use crate::plane0::ines_005::INES_005;
+    pub use crate::plane0::ines_007::INES_007;
+    pub use crate::plane0::ines_009::INES_009;
+    pub use crate::plane0::ines_010::INES_010;
+    pub use crate::plane0::ines_011::INES_011;
+    pub use crate::plane0::ines_016::INES_016;
+    pub use crate::plane0::ines_019::INES_019;
+    pub use crate::plane0::ines_021::INES_021;
+    pub use crate::plane0::ines_024::INES_024;
+    pub use crate::plane0::ines_034::INES_034;
+    pub use crate::plane0::ines_066::INES_066;
+    pub use crate::plane0::ines_069::INES_069;
+    pub use crate::plane0::ines_085::INES_085;
 }
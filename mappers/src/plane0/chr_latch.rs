@@ -0,0 +1,68 @@
+/// Tile triggered CHR banking shared by the MMC2 and MMC4.
+///
+/// Each CHR half has two 4K banks, picked by a latch that flips when the PPU
+/// fetches the pattern of tile $FD or $FE. The new bank only applies from the
+/// access after the one that tripped the latch.
+#[derive(Debug)]
+pub(crate) struct ChrLatch {
+    char_banks: u8,
+    /// The MMC2 low table only reacts to the exact address, the MMC4 one to the whole row range
+    low_row_range: bool,
+
+    /// [$FD bank, $FE bank] for each pattern table
+    banks: [[u8; 2]; 2],
+    /// 0 for $FD, 1 for $FE
+    latch: [usize; 2],
+    pending_latch: Option<(usize, usize)>,
+}
+
+impl ChrLatch {
+    pub fn new_mmc2(char_banks: u8) -> Self {
+        Self::new(char_banks, false)
+    }
+
+    pub fn new_mmc4(char_banks: u8) -> Self {
+        Self::new(char_banks, true)
+    }
+
+    fn new(char_banks: u8, low_row_range: bool) -> Self {
+        Self {
+            char_banks,
+            low_row_range,
+
+            banks: [[0x00; 2]; 2],
+            latch: [1, 1],
+            pending_latch: None,
+        }
+    }
+
+    /// Bank registers at $B000-$EFFF.
+    pub fn write(&mut self, address: u16, data: u8) {
+        let register = ((address - 0xB000) >> 12) as usize;
+        self.banks[register / 2][register % 2] = data & 0x1F;
+    }
+
+    pub fn char_offset(&self, address: u16) -> usize {
+        let table = (address >> 12) as usize & 0x01;
+        let bank = self.banks[table][self.latch[table]];
+
+        (bank as usize % (self.char_banks as usize * 2).max(2)) * 0x1000
+            + (address & 0x0FFF) as usize
+    }
+
+    pub fn ppu_address(&mut self, address: u16) {
+        if let Some((table, value)) = self.pending_latch.take() {
+            self.latch[table] = value;
+        }
+
+        self.pending_latch = match address {
+            0x0FD8 => Some((0, 0)),
+            0x0FE8 => Some((0, 1)),
+            0x0FD9..=0x0FDF if self.low_row_range => Some((0, 0)),
+            0x0FE9..=0x0FEF if self.low_row_range => Some((0, 1)),
+            0x1FD8..=0x1FDF => Some((1, 0)),
+            0x1FE8..=0x1FEF => Some((1, 1)),
+            _ => None,
+        };
+    }
+}
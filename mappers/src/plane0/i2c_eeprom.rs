@@ -0,0 +1,190 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EepromState {
+    /// Waiting for a start condition
+    Idle,
+    /// 24C02 only, the 1010xxx device select with the R/W bit
+    Device,
+    /// Word address, on the 24C01 together with the R/W bit
+    Address,
+    Write,
+    Read,
+}
+
+/// A serial EEPROM bit-banged over I2C by the mapper, either a 24C01 (128 bytes)
+/// or a 24C02 (256 bytes).
+///
+/// The 24C01 is the older Xicor part: it has no device select byte, the first
+/// byte after a start is the word address with the R/W bit on top, and all
+/// bytes go over the wire LSB first. The 24C02 is the usual one.
+#[derive(Debug)]
+pub(crate) struct I2cEeprom {
+    data: Vec<u8>,
+    x24c01: bool,
+
+    scl: bool,
+    sda: bool,
+    /// What the EEPROM drives on SDA, high means released
+    output: bool,
+
+    state: EepromState,
+    /// State to go to once the acknowledge bit is over
+    next: EepromState,
+    /// Clock pulses seen within the current byte, the 9th is the acknowledge bit
+    bit: u8,
+    shift: u8,
+    address: u8,
+}
+
+impl I2cEeprom {
+    pub fn new_24c01() -> Self {
+        Self::new(0x80, true)
+    }
+
+    pub fn new_24c02() -> Self {
+        Self::new(0x100, false)
+    }
+
+    fn new(size: usize, x24c01: bool) -> Self {
+        Self {
+            data: vec![0xFF; size],
+            x24c01,
+
+            scl: false,
+            sda: false,
+            output: true,
+
+            state: EepromState::Idle,
+            next: EepromState::Idle,
+            bit: 0,
+            shift: 0x00,
+            address: 0x00,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// Level of the EEPROM's SDA output.
+    pub fn output(&self) -> bool {
+        self.output
+    }
+
+    /// Sets the SCL and SDA lines driven by the mapper.
+    pub fn write(&mut self, scl: bool, sda: bool) {
+        if self.scl && scl && self.sda != sda {
+            // SDA changing while SCL is high is a start (falling) or stop (rising)
+            self.state = if sda {
+                EepromState::Idle
+            } else if self.x24c01 {
+                EepromState::Address
+            } else {
+                EepromState::Device
+            };
+            self.bit = 0;
+            self.shift = 0x00;
+            self.output = true;
+        } else if !self.scl && scl {
+            self.clock_rise(sda);
+        } else if self.scl && !scl {
+            self.clock_fall();
+        }
+
+        self.scl = scl;
+        self.sda = sda;
+    }
+
+    fn clock_rise(&mut self, sda: bool) {
+        match self.state {
+            EepromState::Idle => return,
+            // The master doesn't acknowledge the last byte it wants, the EEPROM lets go
+            // of SDA and waits for the next start
+            EepromState::Read if self.bit == 8 && sda => {
+                self.state = EepromState::Idle;
+                self.next = EepromState::Idle;
+            }
+            EepromState::Read => {}
+            _ if self.bit >= 8 => {}
+            _ if self.x24c01 => self.shift |= (sda as u8) << self.bit,
+            _ => self.shift = (self.shift << 1) | sda as u8,
+        }
+
+        self.bit += 1;
+    }
+
+    fn clock_fall(&mut self) {
+        match (self.state, self.bit) {
+            (EepromState::Read, 8) => self.output = true,
+            (_, 8) => {
+                let byte = self.shift;
+                self.shift = 0x00;
+                self.output = !self.receive(byte);
+            }
+            (state, 9) => {
+                self.bit = 0;
+                self.output = true;
+
+                if state == EepromState::Read {
+                    self.address = self.address.wrapping_add(1);
+                } else {
+                    self.state = self.next;
+                }
+
+                if self.state == EepromState::Read {
+                    self.output = self.read_bit();
+                }
+            }
+            (EepromState::Read, _) => self.output = self.read_bit(),
+            _ => {}
+        }
+    }
+
+    /// The 24C01 only has 7 address bits.
+    fn index(&self) -> usize {
+        self.address as usize % self.data.len()
+    }
+
+    fn read_bit(&self) -> bool {
+        let byte = self.data[self.index()];
+        let bit = if self.x24c01 { self.bit } else { 7 - self.bit };
+
+        (byte >> bit) & 0x01 != 0
+    }
+
+    /// Handles a whole byte from the master, returns whether to acknowledge it.
+    fn receive(&mut self, byte: u8) -> bool {
+        self.next = match self.state {
+            EepromState::Device if byte & 0xF1 == 0xA1 => EepromState::Read,
+            EepromState::Device if byte & 0xF0 == 0xA0 => EepromState::Address,
+            EepromState::Device => EepromState::Idle,
+            EepromState::Address if self.x24c01 => {
+                self.address = byte & 0x7F;
+                if byte & 0x80 != 0 {
+                    EepromState::Read
+                } else {
+                    EepromState::Write
+                }
+            }
+            EepromState::Address => {
+                self.address = byte;
+                EepromState::Write
+            }
+            EepromState::Write => {
+                let index = self.index();
+                self.data[index] = byte;
+
+                // Writes wrap around within a page, 4 bytes on the 24C01 and 8 on the 24C02
+                let page = if self.x24c01 { 0x03 } else { 0x07 };
+                self.address = (self.address & !page) | (self.address.wrapping_add(1) & page);
+                EepromState::Write
+            }
+            state => state,
+        };
+
+        self.next != EepromState::Idle
+    }
+}
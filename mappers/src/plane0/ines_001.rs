@@ -0,0 +1,174 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+
+/// MMC1, used by the SxROM boards.
+///
+/// Registers are loaded one bit at a time through a 5 bit shift register. The
+/// spare high bits of the CHR bank registers are wired differently per board:
+/// SUROM uses bit 4 as PRG A18 to reach 512K, SOROM/SXROM use bits 2-3 to bank
+/// the 16K/32K PRG-RAM and SNROM uses bit 4 to disable its PRG-RAM.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_001 {
+    prog_banks: u8,
+    char_banks: u8,
+    prog_ram_banks: u8,
+
+    shift: u8,
+    shift_count: u8,
+    control: u8,
+    char_bank_0: u8,
+    char_bank_1: u8,
+    prog_bank: u8,
+
+    /// CPU cycles since the last write to $8000-$FFFF
+    cycles_since_write: u8,
+}
+
+impl INES_001 {
+    pub fn new(prog_banks: u8, char_banks: u8, prog_ram_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+            prog_ram_banks,
+
+            shift: 0x00,
+            shift_count: 0,
+            control: 0x0C,
+            char_bank_0: 0x00,
+            char_bank_1: 0x00,
+            prog_bank: 0x00,
+
+            cycles_since_write: u8::MAX,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x8000..=0x9FFF => self.control = data,
+            0xA000..=0xBFFF => self.char_bank_0 = data,
+            0xC000..=0xDFFF => self.char_bank_1 = data,
+            0xE000..=0xFFFF => self.prog_bank = data,
+            _ => unreachable!(),
+        }
+    }
+
+    fn prog_offset(&self, address: u16) -> usize {
+        // SUROM: the upper 256K is picked by bit 4 of the CHR bank register
+        let outer = if self.prog_banks > 16 {
+            (self.char_bank_0 & 0x10) as usize
+        } else {
+            0
+        };
+
+        let bank = (self.prog_bank & 0x0F) as usize;
+        let bank = match (self.control >> 2) & 0x03 {
+            0 | 1 => (bank & 0x0E) | ((address >> 14) & 0x01) as usize,
+            2 if address < 0xC000 => 0,
+            2 => bank,
+            _ if address < 0xC000 => bank,
+            _ => 0x0F,
+        };
+
+        ((outer | bank) % self.prog_banks.max(1) as usize) * 0x4000 + (address & 0x3FFF) as usize
+    }
+
+    fn ram_offset(&self, address: u16) -> Option<usize> {
+        if self.prog_ram_banks == 0 || self.prog_bank & 0x10 != 0 {
+            return None;
+        }
+
+        // SNROM: 8K CHR-RAM leaves bit 4 free to act as a second RAM enable
+        if self.char_banks == 0 && self.prog_banks <= 16 && self.char_bank_0 & 0x10 != 0 {
+            return None;
+        }
+
+        let bank = match self.prog_ram_banks {
+            2 => (self.char_bank_0 >> 3) & 0x01,
+            4 => (self.char_bank_0 >> 2) & 0x03,
+            _ => 0,
+        };
+
+        Some(bank as usize * 0x2000 + (address & 0x1FFF) as usize)
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        let bank = if self.control & 0x10 == 0 {
+            (self.char_bank_0 & 0x1E) | ((address >> 12) & 0x01) as u8
+        } else if address < 0x1000 {
+            self.char_bank_0
+        } else {
+            self.char_bank_1
+        };
+
+        // Counted in 4K banks, CHR-RAM boards have a single 8K bank
+        let char_banks = (self.char_banks as usize * 2).max(2);
+
+        (bank as usize % char_banks) * 0x1000 + (address & 0x0FFF) as usize
+    }
+}
+
+impl Mapper for INES_001 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF => self.ram_offset(address).map(MappedAddress::Ram),
+            0x8000..=0xFFFF => Some(MappedAddress::Prog(self.prog_offset(address))),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF => self.ram_offset(address).map(MappedAddress::Ram),
+            0x8000..=0xFFFF => {
+                // Only the first write of back to back cycles (e.g. the dummy write
+                // of a read-modify-write instruction) reaches the shift register
+                let consecutive = self.cycles_since_write < 2;
+                self.cycles_since_write = 0;
+
+                if consecutive {
+                    return None;
+                }
+
+                if data & 0x80 != 0 {
+                    self.shift = 0x00;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return None;
+                }
+
+                self.shift = (self.shift >> 1) | ((data & 0x01) << 4);
+                self.shift_count += 1;
+
+                if self.shift_count == 5 {
+                    self.write_register(address, self.shift);
+                    self.shift = 0x00;
+                    self.shift_count = 0;
+                }
+
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(match self.control & 0x03 {
+            0 => Mirror::OnescreenLo,
+            1 => Mirror::OnescreenHi,
+            2 => Mirror::Vertical,
+            _ => Mirror::Horizontal,
+        })
+    }
+
+    fn cpu_clock(&mut self) {
+        self.cycles_since_write = self.cycles_since_write.saturating_add(1);
+    }
+}
@@ -0,0 +1,52 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper};
+
+/// UxROM, a 16K switchable bank at $8000 and the last bank fixed at $C000.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_002 {
+    prog_banks: u8,
+    prog_bank: u8,
+}
+
+impl INES_002 {
+    pub fn new(prog_banks: u8, _char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            prog_bank: 0x00,
+        }
+    }
+}
+
+impl Mapper for INES_002 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let bank = match address {
+            0x8000..=0xBFFF => self.prog_bank % self.prog_banks.max(1),
+            0xC000..=0xFFFF => self.prog_banks.saturating_sub(1),
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            bank as usize * 0x4000 + (address & 0x3FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            self.prog_bank = data;
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(address as usize));
+        }
+
+        None
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        true
+    }
+}
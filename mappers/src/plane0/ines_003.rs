@@ -0,0 +1,55 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper};
+
+/// CNROM, fixed PRG like NROM with a switchable 8K CHR bank.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_003 {
+    prog_banks: u8,
+    char_banks: u8,
+    char_bank: u8,
+}
+
+impl INES_003 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+            char_bank: 0x00,
+        }
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        (self.char_bank % self.char_banks.max(1)) as usize * 0x2000 + address as usize
+    }
+}
+
+impl Mapper for INES_003 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            let mask = if self.prog_banks > 1 { 0x7FFF } else { 0x3FFF };
+            return Some(MappedAddress::Prog((address & mask) as usize));
+        }
+
+        None
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            self.char_bank = data;
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        true
+    }
+}
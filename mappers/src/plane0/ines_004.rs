@@ -0,0 +1,187 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+
+/// MMC3, used by the TxROM boards.
+///
+/// The scanline counter is clocked by rising edges of PPU A12. Like the real
+/// chip, an edge only counts after A12 has been low for a few CPU cycles, which
+/// filters out the short blips between the sprite pattern fetches.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_004 {
+    prog_banks: u8,
+    char_banks: u8,
+
+    bank_select: u8,
+    banks: [u8; 8],
+    mirror: Mirror,
+    prog_ram_enabled: bool,
+    prog_ram_protected: bool,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_active: bool,
+
+    a12: bool,
+    /// CPU cycles A12 has stayed low for
+    a12_low_cycles: u8,
+}
+
+impl INES_004 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+
+            bank_select: 0x00,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirror: Mirror::Vertical,
+            prog_ram_enabled: true,
+            prog_ram_protected: false,
+
+            irq_latch: 0x00,
+            irq_counter: 0x00,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_active: false,
+
+            a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    fn prog_offset(&self, address: u16) -> usize {
+        let banks = (self.prog_banks as usize * 2).max(1);
+        let second_last = banks.saturating_sub(2);
+
+        let bank = match (address >> 13) & 0x03 {
+            0 if self.bank_select & 0x40 != 0 => second_last,
+            0 => self.banks[6] as usize,
+            1 => self.banks[7] as usize,
+            2 if self.bank_select & 0x40 != 0 => self.banks[6] as usize,
+            2 => second_last,
+            _ => banks - 1,
+        };
+
+        (bank % banks) * 0x2000 + (address & 0x1FFF) as usize
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        // CHR inversion swaps the 2K and 1K halves
+        let address = if self.bank_select & 0x80 != 0 {
+            address ^ 0x1000
+        } else {
+            address
+        };
+
+        let bank = match address >> 10 {
+            0 => self.banks[0] & 0xFE,
+            1 => self.banks[0] | 0x01,
+            2 => self.banks[1] & 0xFE,
+            3 => self.banks[1] | 0x01,
+            n => self.banks[(n - 2) as usize],
+        };
+
+        let banks = (self.char_banks as usize * 8).max(8);
+
+        (bank as usize % banks) * 0x0400 + (address & 0x03FF) as usize
+    }
+
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_active = true;
+        }
+    }
+}
+
+impl Mapper for INES_004 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF if self.prog_ram_enabled => {
+                Some(MappedAddress::Ram((address & 0x1FFF) as usize))
+            }
+            0x8000..=0xFFFF => Some(MappedAddress::Prog(self.prog_offset(address))),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        let even = address & 0x01 == 0;
+
+        match address {
+            0x6000..=0x7FFF if self.prog_ram_enabled && !self.prog_ram_protected => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize));
+            }
+            0x8000..=0x9FFF if even => self.bank_select = data,
+            0x8000..=0x9FFF => self.banks[(self.bank_select & 0x07) as usize] = data,
+            0xA000..=0xBFFF if even => {
+                self.mirror = if data & 0x01 == 0 {
+                    Mirror::Vertical
+                } else {
+                    Mirror::Horizontal
+                };
+            }
+            0xA000..=0xBFFF => {
+                self.prog_ram_enabled = data & 0x80 != 0;
+                self.prog_ram_protected = data & 0x40 != 0;
+            }
+            0xC000..=0xDFFF if even => self.irq_latch = data,
+            0xC000..=0xDFFF => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            0xE000..=0xFFFF if even => {
+                self.irq_enabled = false;
+                self.irq_active = false;
+            }
+            0xE000..=0xFFFF => self.irq_enabled = true,
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+
+    fn cpu_clock(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+
+        if a12 && !self.a12 && self.a12_low_cycles >= 3 {
+            self.clock_irq_counter();
+        }
+
+        if a12 {
+            self.a12_low_cycles = 0;
+        }
+
+        self.a12 = a12;
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_active
+    }
+}
@@ -0,0 +1,404 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper};
+
+/// MMC5, used by the ExROM boards.
+///
+/// The chip has no scanline or sprite signal from the PPU, so like the real
+/// thing it watches the PPU bus: three identical nametable fetches in a row at
+/// the end of every rendered line mark a new scanline, counting nametable fetches after
+/// that tells background tiles from the sprite fetches in between, and the bus
+/// going quiet for a few CPU cycles means the PPU left the visible frame.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_005 {
+    prog_banks: u8,
+    char_banks: u8,
+    prog_ram_banks: u8,
+
+    prog_mode: u8,
+    char_mode: u8,
+    prog_ram_protect: [u8; 2],
+    /// $5113-$5117
+    prog_regs: [u8; 5],
+    /// $5120-$5127 (set A) followed by $5128-$512B (set B), with the $5130 bits on top
+    char_regs: [u16; 12],
+    char_upper: u8,
+    last_char_set_b: bool,
+    sprite_8x16: bool,
+
+    exram: [u8; 0x400],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    in_frame: bool,
+    scanline: u8,
+
+    multiplicand: u8,
+    multiplier: u8,
+
+    last_nametable_address: u16,
+    nametable_repeats: u8,
+    /// Nametable fetches since the current scanline was detected
+    nametable_fetches: u8,
+    /// CPU cycles without the PPU reading anything
+    ppu_idle_cycles: u8,
+    /// ExRAM byte of the tile being fetched, for the extended attribute mode
+    ext_attribute: u8,
+    /// Split screen position (x tile, y pixel) of the tile being fetched
+    split_tile: Option<(u8, u8)>,
+}
+
+impl INES_005 {
+    pub fn new(prog_banks: u8, char_banks: u8, prog_ram_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+            prog_ram_banks,
+
+            prog_mode: 0x03,
+            char_mode: 0x03,
+            prog_ram_protect: [0x00; 2],
+            prog_regs: [0x00, 0x00, 0x00, 0x00, 0xFF],
+            char_regs: [0x00; 12],
+            char_upper: 0x00,
+            last_char_set_b: false,
+            sprite_8x16: false,
+
+            exram: [0x00; 0x400],
+            exram_mode: 0x00,
+            nametable_mapping: 0x00,
+            fill_tile: 0x00,
+            fill_attribute: 0x00,
+
+            split_control: 0x00,
+            split_scroll: 0x00,
+            split_bank: 0x00,
+
+            irq_compare: 0x00,
+            irq_enabled: false,
+            irq_pending: false,
+            in_frame: false,
+            scanline: 0,
+
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+
+            last_nametable_address: 0x0000,
+            nametable_repeats: 0,
+            nametable_fetches: 0,
+            ppu_idle_cycles: 0,
+            ext_attribute: 0x00,
+            split_tile: None,
+        }
+    }
+
+    /// Maps $6000-$FFFF, `Err` holds a PRG-RAM offset.
+    fn prog_offset(&self, address: u16) -> Result<usize, usize> {
+        if address < 0x8000 {
+            return Err(self.ram_offset(self.prog_regs[0], address));
+        }
+
+        let slot = ((address - 0x8000) >> 13) as usize;
+
+        // Register used and the size of the window in 8K banks
+        let (reg, size) = match (self.prog_mode, slot) {
+            (0, _) => (4, 4),
+            (1, 0 | 1) => (2, 2),
+            (1, _) => (4, 2),
+            (2, 0 | 1) => (2, 2),
+            (2, 2) => (3, 1),
+            (2, _) => (4, 1),
+            (_, n) => (n + 1, 1),
+        };
+
+        let value = self.prog_regs[reg];
+        let bank = (value as usize & 0x7F & !(size - 1)) | (slot & (size - 1));
+
+        // $5117 always maps ROM
+        if reg == 4 || value & 0x80 != 0 {
+            let banks = (self.prog_banks as usize * 2).max(1);
+            Ok((bank % banks) * 0x2000 + (address & 0x1FFF) as usize)
+        } else {
+            Err(self.ram_offset(bank as u8, address))
+        }
+    }
+
+    fn ram_offset(&self, bank: u8, address: u16) -> usize {
+        let bank = (bank & 0x07) as usize % self.prog_ram_banks.max(1) as usize;
+
+        bank * 0x2000 + (address & 0x1FFF) as usize
+    }
+
+    fn prog_ram_writable(&self) -> bool {
+        self.prog_ram_protect == [0x02, 0x01]
+    }
+
+    /// The PPU is somewhere between the sprite fetches and the next line's first tiles.
+    fn sprite_fetch(&self) -> bool {
+        self.in_frame && self.nametable_fetches == 32
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        let char_size = (self.char_banks as usize * 0x2000).max(0x2000);
+        let sprite = self.sprite_fetch();
+
+        if self.in_frame && !sprite {
+            if let Some((_, y)) = self.split_tile {
+                let offset = self.split_bank as usize * 0x1000
+                    + (address & 0x0FF8) as usize
+                    + (y & 0x07) as usize;
+                return offset % char_size;
+            }
+
+            if self.exram_mode == 1 {
+                let bank =
+                    (self.ext_attribute & 0x3F) as usize | ((self.char_upper & 0x03) as usize) << 6;
+                return (bank * 0x1000 + (address & 0x0FFF) as usize) % char_size;
+            }
+        }
+
+        // Set B only exists for the background of 8x16 sprite frames
+        let set_b = if !self.in_frame {
+            self.last_char_set_b
+        } else {
+            self.sprite_8x16 && !sprite
+        };
+
+        let (address, first, slots) = if set_b {
+            (address & 0x0FFF, 8, 4)
+        } else {
+            (address, 0, 8)
+        };
+
+        let size = 0x2000_usize >> self.char_mode;
+        let per_window = (8 >> self.char_mode).min(slots);
+        let index = first + (address as usize / size + 1) * per_window - 1;
+
+        (self.char_regs[index] as usize * size + address as usize % size) % char_size
+    }
+
+    fn detect_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+
+            if self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+            self.irq_pending = false;
+        }
+
+        self.nametable_fetches = 0;
+    }
+
+    fn fetch_tile(&mut self, address: u16) {
+        self.nametable_fetches = self.nametable_fetches.saturating_add(1);
+
+        // The first three tiles of a line are fetched at the end of the previous one
+        let tile = self.nametable_fetches.saturating_add(2);
+        let (tile_x, line) = if tile >= 35 {
+            (tile - 35, self.scanline as u16 + 1)
+        } else {
+            (tile, self.scanline as u16)
+        };
+
+        let count = self.split_control & 0x1F;
+        let inside = if self.split_control & 0x40 != 0 {
+            tile_x >= count
+        } else {
+            tile_x < count
+        };
+
+        self.split_tile =
+            if self.in_frame && self.split_control & 0x80 != 0 && self.exram_mode <= 1 && inside {
+                let y = (self.split_scroll as u16 + line) % 240;
+                Some((tile_x, y as u8))
+            } else {
+                None
+            };
+
+        self.ext_attribute = self.exram[(address & 0x03FF) as usize];
+    }
+}
+
+impl Mapper for INES_005 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        match address {
+            0x5204 => {
+                let status = (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6;
+                self.irq_pending = false;
+
+                Some(MappedAddress::Data(status))
+            }
+            0x5205 => {
+                let product = self.multiplicand as u16 * self.multiplier as u16;
+                Some(MappedAddress::Data(product as u8))
+            }
+            0x5206 => {
+                let product = self.multiplicand as u16 * self.multiplier as u16;
+                Some(MappedAddress::Data((product >> 8) as u8))
+            }
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => {
+                Some(MappedAddress::Data(self.exram[(address & 0x03FF) as usize]))
+            }
+            0x6000..=0xFFFF => Some(match self.prog_offset(address) {
+                Ok(offset) => MappedAddress::Prog(offset),
+                Err(offset) => MappedAddress::Ram(offset),
+            }),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            // PPUCTRL is snooped for the sprite size
+            0x2000..=0x3FFF if address & 0x07 == 0 => self.sprite_8x16 = data & 0x20 != 0,
+            0x5100 => self.prog_mode = data & 0x03,
+            0x5101 => self.char_mode = data & 0x03,
+            0x5102 => self.prog_ram_protect[0] = data & 0x03,
+            0x5103 => self.prog_ram_protect[1] = data & 0x03,
+            0x5104 => self.exram_mode = data & 0x03,
+            0x5105 => self.nametable_mapping = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attribute = data & 0x03,
+            0x5113..=0x5117 => self.prog_regs[(address - 0x5113) as usize] = data,
+            0x5120..=0x512B => {
+                self.char_regs[(address - 0x5120) as usize] =
+                    data as u16 | ((self.char_upper & 0x03) as u16) << 8;
+                self.last_char_set_b = address >= 0x5128;
+            }
+            0x5130 => self.char_upper = data & 0x03,
+            0x5200 => self.split_control = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_bank = data,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enabled = data & 0x80 != 0,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5C00..=0x5FFF => {
+                let index = (address & 0x03FF) as usize;
+
+                // As a nametable ExRAM can only be written while the PPU is rendering
+                match self.exram_mode {
+                    0 | 1 => self.exram[index] = if self.in_frame { data } else { 0x00 },
+                    2 => self.exram[index] = data,
+                    _ => {}
+                }
+            }
+            0x6000..=0xFFFF => {
+                if let Err(offset) = self.prog_offset(address) {
+                    if self.prog_ram_writable() {
+                        return Some(MappedAddress::Ram(offset));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        let index = (address & 0x03FF) as usize;
+        let attribute = index >= 0x03C0;
+
+        if let Some((x, y)) = self.split_tile.filter(|_| self.in_frame) {
+            let (x, coarse_y) = (x as usize, (y >> 3) as usize);
+
+            return Some(MappedPpuAddress::Data(if attribute {
+                let byte = self.exram[0x03C0 + (coarse_y >> 2) * 8 + (x >> 2)];
+                let shift = ((coarse_y & 0x02) << 1) | (x & 0x02);
+                ((byte >> shift) & 0x03) * 0x55
+            } else {
+                self.exram[coarse_y * 32 + x]
+            }));
+        }
+
+        // Every quadrant of the attribute byte gets the tile's palette
+        if attribute && self.exram_mode == 1 && self.in_frame {
+            return Some(MappedPpuAddress::Data((self.ext_attribute >> 6) * 0x55));
+        }
+
+        Some(
+            match (self.nametable_mapping >> (((address >> 10) & 0x03) * 2)) & 0x03 {
+                0 => MappedPpuAddress::Ciram(0),
+                1 => MappedPpuAddress::Ciram(1),
+                2 if self.exram_mode <= 1 => MappedPpuAddress::Data(self.exram[index]),
+                2 => MappedPpuAddress::Data(0x00),
+                _ if attribute => MappedPpuAddress::Data(self.fill_attribute * 0x55),
+                _ => MappedPpuAddress::Data(self.fill_tile),
+            },
+        )
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        let index = (address & 0x03FF) as usize;
+
+        Some(
+            match (self.nametable_mapping >> (((address >> 10) & 0x03) * 2)) & 0x03 {
+                0 => MappedPpuAddress::Ciram(0),
+                1 => MappedPpuAddress::Ciram(1),
+                2 => {
+                    if self.exram_mode <= 1 {
+                        self.exram[index] = data;
+                    }
+                    MappedPpuAddress::Data(data)
+                }
+                _ => MappedPpuAddress::Data(data),
+            },
+        )
+    }
+
+    fn cpu_clock(&mut self) {
+        self.ppu_idle_cycles = self.ppu_idle_cycles.saturating_add(1);
+
+        if self.ppu_idle_cycles >= 3 {
+            self.in_frame = false;
+            self.nametable_fetches = 0;
+            self.last_nametable_address = 0x0000;
+            self.nametable_repeats = 0;
+            self.split_tile = None;
+        }
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        self.ppu_idle_cycles = 0;
+
+        if (0x2000..=0x2FFF).contains(&address) && address & 0x03FF < 0x03C0 {
+            if address != self.last_nametable_address {
+                self.nametable_repeats = 0;
+                self.fetch_tile(address);
+            } else {
+                self.nametable_repeats = self.nametable_repeats.saturating_add(1);
+
+                if self.nametable_repeats == 2 {
+                    self.detect_scanline();
+                }
+            }
+
+            self.last_nametable_address = address;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending && self.irq_enabled
+    }
+}
@@ -0,0 +1,60 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+
+/// AxROM, a 32K switchable PRG bank and a register selected single-screen nametable.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_007 {
+    prog_banks: u8,
+    prog_bank: u8,
+    mirror: Mirror,
+}
+
+impl INES_007 {
+    pub fn new(prog_banks: u8, _char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            prog_bank: 0x00,
+            mirror: Mirror::OnescreenLo,
+        }
+    }
+}
+
+impl Mapper for INES_007 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            let banks = (self.prog_banks as usize / 2).max(1);
+            let bank = (self.prog_bank & 0x0F) as usize % banks;
+
+            return Some(MappedAddress::Prog(
+                bank * 0x8000 + (address & 0x7FFF) as usize,
+            ));
+        }
+
+        None
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            self.prog_bank = data & 0x0F;
+            self.mirror = if data & 0x10 == 0 {
+                Mirror::OnescreenLo
+            } else {
+                Mirror::OnescreenHi
+            };
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(address as usize));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+}
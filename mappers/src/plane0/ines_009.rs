@@ -0,0 +1,76 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+use super::chr_latch::ChrLatch;
+
+/// MMC2, used by PxROM (Punch-Out!!).
+///
+/// Each CHR half has two 4K banks switched by tile fetches, see [`ChrLatch`].
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_009 {
+    prog_banks: u8,
+
+    prog_bank: u8,
+    chr: ChrLatch,
+    mirror: Mirror,
+}
+
+impl INES_009 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+
+            prog_bank: 0x00,
+            chr: ChrLatch::new_mmc2(char_banks),
+            mirror: Mirror::Vertical,
+        }
+    }
+}
+
+impl Mapper for INES_009 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        // One switchable 8K bank followed by the last three
+        let banks = (self.prog_banks as usize * 2).max(4);
+        let bank = match address {
+            0x8000..=0x9FFF => self.prog_bank as usize % banks,
+            0xA000..=0xFFFF => banks - 4 + ((address - 0x8000) >> 13) as usize,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            bank * 0x2000 + (address & 0x1FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0xA000..=0xAFFF => self.prog_bank = data & 0x0F,
+            0xB000..=0xEFFF => self.chr.write(address, data),
+            0xF000..=0xFFFF => {
+                self.mirror = if data & 0x01 == 0 {
+                    Mirror::Vertical
+                } else {
+                    Mirror::Horizontal
+                };
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.chr.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        self.chr.ppu_address(address);
+    }
+}
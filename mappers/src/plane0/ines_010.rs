@@ -0,0 +1,76 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+use super::chr_latch::ChrLatch;
+
+/// MMC4, used by FxROM.
+///
+/// Same CHR latches as MMC2, but with a 16K PRG bank and 8K of PRG-RAM.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_010 {
+    prog_banks: u8,
+
+    prog_bank: u8,
+    chr: ChrLatch,
+    mirror: Mirror,
+}
+
+impl INES_010 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+
+            prog_bank: 0x00,
+            chr: ChrLatch::new_mmc4(char_banks),
+            mirror: Mirror::Vertical,
+        }
+    }
+}
+
+impl Mapper for INES_010 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let bank = match address {
+            0x6000..=0x7FFF => return Some(MappedAddress::Ram((address & 0x1FFF) as usize)),
+            0x8000..=0xBFFF => self.prog_bank % self.prog_banks.max(1),
+            0xC000..=0xFFFF => self.prog_banks.saturating_sub(1),
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            bank as usize * 0x4000 + (address & 0x3FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF => return Some(MappedAddress::Ram((address & 0x1FFF) as usize)),
+            0xA000..=0xAFFF => self.prog_bank = data & 0x0F,
+            0xB000..=0xEFFF => self.chr.write(address, data),
+            0xF000..=0xFFFF => {
+                self.mirror = if data & 0x01 == 0 {
+                    Mirror::Vertical
+                } else {
+                    Mirror::Horizontal
+                };
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.chr.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+
+    fn ppu_address(&mut self, address: u16) {
+        self.chr.ppu_address(address);
+    }
+}
@@ -0,0 +1,59 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper};
+
+/// Color Dreams, a single latch holding a 32K PRG bank (bits 0-1) and an 8K CHR bank (bits 4-7).
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_011 {
+    prog_banks: u8,
+    char_banks: u8,
+    latch: u8,
+}
+
+impl INES_011 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+            latch: 0x00,
+        }
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        ((self.latch >> 4) % self.char_banks.max(1)) as usize * 0x2000 + address as usize
+    }
+}
+
+impl Mapper for INES_011 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            let banks = (self.prog_banks as usize / 2).max(1);
+            let bank = (self.latch & 0x03) as usize % banks;
+
+            return Some(MappedAddress::Prog(
+                bank * 0x8000 + (address & 0x7FFF) as usize,
+            ));
+        }
+
+        None
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        if address >= 0x8000 {
+            self.latch = data;
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        true
+    }
+}
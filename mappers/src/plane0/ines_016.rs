@@ -0,0 +1,229 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+use super::i2c_eeprom::I2cEeprom;
+
+/// Bandai FCG-1/2 and LZ93D50, iNES mappers 16, 153, 157 and 159.
+///
+/// The FCG chips have their registers at $6000 and the LZ93D50 moved them to
+/// $8000. Saves are kept on a serial EEPROM the game bit-bangs through $800D:
+/// a 24C02 on mapper 16 and 157, a 24C01 on mapper 159. Mapper 153 has battery
+/// backed PRG-RAM instead and uses the CHR registers for a 256K outer PRG bank.
+/// The Datach barcode reader and its second EEPROM on mapper 157 are not
+/// emulated.
+///
+/// Mapper 16 without a submapper can be either chip. It listens to both
+/// register ranges, which games for either are fine with, and only gets a
+/// 24C02 when the header has the battery bit set since the FCG boards have no
+/// save memory at all.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_016 {
+    prog_banks: u8,
+    char_banks: u8,
+    mapper_id: u8,
+    /// Register ranges: $6000-$7FFF on the FCG, $8000-$FFFF on the LZ93D50
+    fcg_registers: bool,
+    lz93d50_registers: bool,
+
+    prog_bank: u8,
+    char_bank: [u8; 8],
+    mirror: Mirror,
+    /// $800D: EEPROM lines on most boards, PRG-RAM enable on mapper 153
+    control: u8,
+
+    irq_enabled: bool,
+    irq_counter: u16,
+    /// The LZ93D50 loads the counter from a latch when the IRQ gets enabled
+    irq_latch: u16,
+    irq_active: bool,
+
+    eeprom: Option<I2cEeprom>,
+}
+
+impl INES_016 {
+    pub fn new(
+        mapper_id: u8,
+        submapper: u8,
+        battery: bool,
+        prog_banks: u8,
+        char_banks: u8,
+    ) -> Self {
+        let (fcg_registers, lz93d50_registers) = match (mapper_id, submapper) {
+            (16, 4) => (true, false),
+            (16, 5) | (153 | 157 | 159, _) => (false, true),
+            _ => (true, true),
+        };
+
+        let eeprom = match (mapper_id, submapper) {
+            (16, 4) | (153, _) => None,
+            (16, 0) if !battery => None,
+            (159, _) => Some(I2cEeprom::new_24c01()),
+            _ => Some(I2cEeprom::new_24c02()),
+        };
+
+        Self {
+            prog_banks,
+            char_banks,
+            mapper_id,
+            fcg_registers,
+            lz93d50_registers,
+
+            prog_bank: 0x00,
+            char_bank: [0x00; 8],
+            mirror: Mirror::Vertical,
+            control: 0x00,
+
+            irq_enabled: false,
+            irq_counter: 0x0000,
+            irq_latch: 0x0000,
+            irq_active: false,
+
+            eeprom,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        // The FCG writes the counter itself, the LZ93D50 a latch
+        let latched = address >= 0x8000;
+
+        match address & 0x000F {
+            0x00..=0x07 => self.char_bank[(address & 0x07) as usize] = data,
+            0x08 => self.prog_bank = data & 0x0F,
+            0x09 => {
+                self.mirror = match data & 0x03 {
+                    0 => Mirror::Vertical,
+                    1 => Mirror::Horizontal,
+                    2 => Mirror::OnescreenLo,
+                    _ => Mirror::OnescreenHi,
+                };
+            }
+            0x0A => {
+                self.irq_enabled = data & 0x01 != 0;
+                self.irq_active = false;
+
+                if latched {
+                    self.irq_counter = self.irq_latch;
+                }
+            }
+            0x0B if latched => self.irq_latch = (self.irq_latch & 0xFF00) | data as u16,
+            0x0C if latched => self.irq_latch = (self.irq_latch & 0x00FF) | (data as u16) << 8,
+            0x0B => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+            0x0C => self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16) << 8,
+            0x0D => {
+                self.control = data;
+
+                if let Some(eeprom) = self.eeprom.as_mut() {
+                    eeprom.write(data & 0x20 != 0, data & 0x40 != 0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 256K PRG bank picked by bit 0 of the CHR registers on mapper 153.
+    fn outer_bank(&self) -> usize {
+        if self.mapper_id != 153 {
+            return 0;
+        }
+
+        (self
+            .char_bank
+            .iter()
+            .fold(0, |bank, char_bank| bank | char_bank)
+            & 0x01) as usize
+    }
+
+    fn prog_ram_enabled(&self) -> bool {
+        self.mapper_id == 153 && self.control & 0x20 != 0
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        // Boards with CHR-RAM don't bank it
+        if self.char_banks == 0 {
+            return address as usize;
+        }
+
+        let bank = self.char_bank[(address >> 10) as usize] as usize;
+        let banks = self.char_banks as usize * 8;
+
+        (bank % banks) * 0x0400 + (address & 0x03FF) as usize
+    }
+}
+
+impl Mapper for INES_016 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let banks = (self.prog_banks as usize).max(1);
+
+        let bank = match address {
+            0x6000..=0x7FFF if self.prog_ram_enabled() => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize))
+            }
+            0x6000..=0x7FFF => {
+                // SDA shows up on bit 4. The line is a wired-AND of both sides: the
+                // mapper drives bit 6 onto it unless bit 7 lets go of it, the EEPROM
+                // pulls it low when it outputs a 0
+                let eeprom = self.eeprom.as_ref()?;
+                let mapper_sda = self.control & 0x80 != 0 || self.control & 0x40 != 0;
+
+                return Some(MappedAddress::Data(
+                    ((mapper_sda && eeprom.output()) as u8) << 4,
+                ));
+            }
+            0x8000..=0xBFFF => self.outer_bank() * 0x10 + self.prog_bank as usize,
+            0xC000..=0xFFFF => self.outer_bank() * 0x10 + 0x0F,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            (bank % banks) * 0x4000 + (address & 0x3FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF if self.prog_ram_enabled() => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize));
+            }
+            0x6000..=0x7FFF if self.fcg_registers => self.write_register(address, data),
+            0x8000..=0xFFFF if self.lz93d50_registers => self.write_register(address, data),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+
+    fn cpu_clock(&mut self) {
+        if !self.irq_enabled {
+            return;
+        }
+
+        self.irq_counter = self.irq_counter.wrapping_sub(1);
+
+        if self.irq_counter == 0 {
+            self.irq_active = true;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_active
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.eeprom.as_ref().map(I2cEeprom::data)
+    }
+
+    fn battery_ram_mut(&mut self) -> Option<&mut [u8]> {
+        self.eeprom.as_mut().map(I2cEeprom::data_mut)
+    }
+}
@@ -0,0 +1,251 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper};
+
+/// Mix level of one unit of the (sample - 8) * volume a channel outputs
+const N163_LEVEL: f32 = 0.0015;
+/// CPU cycles the sound unit spends on each channel
+const N163_CHANNEL_CYCLES: u8 = 15;
+
+/// Namco 163.
+///
+/// CHR and nametable banks can each point at either CHR or one of the CIRAM
+/// pages. The wavetable synth updates one channel every 15 CPU cycles and
+/// plays them one after another, so each of the up to 8 channels gets quieter
+/// and lower pitched the more of them are enabled. The channel registers and
+/// the wave samples share the 128 byte sound RAM, which is battery backed on
+/// carts that have a battery.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_019 {
+    prog_banks: u8,
+    char_banks: u8,
+
+    prog_bank: [u8; 3],
+    char_bank: [u8; 8],
+    nametable_bank: [u8; 4],
+    /// $E800 bits 6-7, keep each pattern table half on CHR even for banks $E0-$FF
+    ciram_disable: [bool; 2],
+    /// $F800, sound RAM address and PRG-RAM write protection
+    address_port: u8,
+
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq_active: bool,
+
+    sound_ram: [u8; 0x80],
+    sound_disabled: bool,
+    sound_cycles: u8,
+    channel: u8,
+    outputs: [i16; 8],
+}
+
+impl INES_019 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+
+            prog_bank: [0x00, 0x01, 0x02],
+            char_bank: [0x00; 8],
+            nametable_bank: [0xE0, 0xE1, 0xE0, 0xE1],
+            ciram_disable: [false; 2],
+            address_port: 0x00,
+
+            irq_counter: 0x0000,
+            irq_enabled: false,
+            irq_active: false,
+
+            sound_ram: [0x00; 0x80],
+            sound_disabled: false,
+            sound_cycles: 0,
+            channel: 7,
+            outputs: [0; 8],
+        }
+    }
+
+    fn char_size(&self) -> usize {
+        (self.char_banks as usize * 0x2000).max(0x2000)
+    }
+
+    /// CIRAM page a pattern table slot shows, if any.
+    fn char_ciram(&self, address: u16) -> Option<usize> {
+        let bank = self.char_bank[(address >> 10) as usize];
+
+        (bank >= 0xE0 && !self.ciram_disable[(address >> 12) as usize])
+            .then_some(bank as usize & 0x01)
+    }
+
+    fn char_offset(&self, bank: u8, address: u16) -> usize {
+        (bank as usize * 0x0400 + (address & 0x03FF) as usize) % self.char_size()
+    }
+
+    fn channel_count(&self) -> u8 {
+        ((self.sound_ram[0x7F] >> 4) & 0x07) + 1
+    }
+
+    fn sound_port(&mut self) -> usize {
+        let address = (self.address_port & 0x7F) as usize;
+
+        if self.address_port & 0x80 != 0 {
+            self.address_port = 0x80 | (self.address_port.wrapping_add(1) & 0x7F);
+        }
+
+        address
+    }
+
+    fn prog_ram_writable(&self, address: u16) -> bool {
+        // Writes need $4x in the upper nibble, the low bits protect 2K each
+        let chunk = (address - 0x6000) >> 11;
+        self.address_port & 0xF0 == 0x40 && self.address_port & (1 << chunk) == 0
+    }
+
+    fn update_channel(&mut self, channel: u8) {
+        let base = 0x40 + channel as usize * 8;
+        let ram = &mut self.sound_ram;
+
+        let frequency =
+            ram[base] as u32 | (ram[base + 2] as u32) << 8 | ((ram[base + 4] & 0x03) as u32) << 16;
+        let mut phase =
+            ram[base + 1] as u32 | (ram[base + 3] as u32) << 8 | (ram[base + 5] as u32) << 16;
+        let length = 256 - (ram[base + 4] & 0xFC) as u32;
+
+        phase = (phase + frequency) % (length << 16);
+        ram[base + 1] = phase as u8;
+        ram[base + 3] = (phase >> 8) as u8;
+        ram[base + 5] = (phase >> 16) as u8;
+
+        // Samples are 4 bit, low nibble first
+        let sample_address = (ram[base + 6] as u32 + (phase >> 16)) & 0xFF;
+        let sample = (ram[(sample_address >> 1) as usize] >> ((sample_address & 0x01) * 4)) & 0x0F;
+        let volume = ram[base + 7] & 0x0F;
+
+        self.outputs[channel as usize] = (sample as i16 - 8) * volume as i16;
+    }
+}
+
+impl Mapper for INES_019 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let banks = (self.prog_banks as usize * 2).max(1);
+
+        let bank = match address {
+            0x4800..=0x4FFF => {
+                let index = self.sound_port();
+                return Some(MappedAddress::Data(self.sound_ram[index]));
+            }
+            0x5000..=0x57FF => return Some(MappedAddress::Data(self.irq_counter as u8)),
+            0x5800..=0x5FFF => {
+                let data = (self.irq_counter >> 8) as u8 | (self.irq_enabled as u8) << 7;
+                return Some(MappedAddress::Data(data));
+            }
+            0x6000..=0x7FFF => return Some(MappedAddress::Ram((address & 0x1FFF) as usize)),
+            0x8000..=0xDFFF => self.prog_bank[((address - 0x8000) >> 13) as usize] as usize,
+            0xE000..=0xFFFF => banks - 1,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            (bank % banks) * 0x2000 + (address & 0x1FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x4800..=0x4FFF => {
+                let index = self.sound_port();
+                self.sound_ram[index] = data;
+            }
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | data as u16;
+                self.irq_active = false;
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | ((data & 0x7F) as u16) << 8;
+                self.irq_enabled = data & 0x80 != 0;
+                self.irq_active = false;
+            }
+            0x6000..=0x7FFF if self.prog_ram_writable(address) => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize));
+            }
+            0x8000..=0xBFFF => self.char_bank[((address - 0x8000) >> 11) as usize] = data,
+            0xC000..=0xDFFF => self.nametable_bank[((address - 0xC000) >> 11) as usize] = data,
+            0xE000..=0xE7FF => {
+                self.prog_bank[0] = data & 0x3F;
+                self.sound_disabled = data & 0x40 != 0;
+            }
+            0xE800..=0xEFFF => {
+                self.prog_bank[1] = data & 0x3F;
+                self.ciram_disable = [data & 0x40 != 0, data & 0x80 != 0];
+            }
+            0xF000..=0xF7FF => self.prog_bank[2] = data & 0x3F,
+            0xF800..=0xFFFF => self.address_port = data,
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            let bank = self.char_bank[(address >> 10) as usize];
+            return Some(match self.char_ciram(address) {
+                Some(page) => MappedPpuAddress::Ciram(page),
+                None => MappedPpuAddress::Chr(self.char_offset(bank, address)),
+            });
+        }
+
+        let bank = self.nametable_bank[((address >> 10) & 0x03) as usize];
+
+        Some(if bank >= 0xE0 {
+            MappedPpuAddress::Ciram(bank as usize & 0x01)
+        } else {
+            MappedPpuAddress::Chr(self.char_offset(bank, address))
+        })
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.irq_enabled && self.irq_counter < 0x7FFF {
+            self.irq_counter += 1;
+
+            if self.irq_counter == 0x7FFF {
+                self.irq_active = true;
+            }
+        }
+
+        if self.sound_disabled {
+            return;
+        }
+
+        self.sound_cycles += 1;
+
+        if self.sound_cycles == N163_CHANNEL_CYCLES {
+            self.sound_cycles = 0;
+            self.update_channel(self.channel);
+
+            // Channels run from 7 down to the lowest enabled one
+            self.channel = if self.channel <= 8 - self.channel_count() {
+                7
+            } else {
+                self.channel - 1
+            };
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_active
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        Some(&self.sound_ram)
+    }
+
+    fn battery_ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.sound_ram)
+    }
+
+    fn audio_output(&self) -> f32 {
+        // Time multiplexed, each enabled channel is only heard for its share of the time
+        let count = self.channel_count();
+        let sum: i16 = self.outputs[(8 - count) as usize..].iter().sum();
+
+        sum as f32 / count as f32 * N163_LEVEL
+    }
+}
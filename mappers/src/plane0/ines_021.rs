@@ -0,0 +1,196 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+use super::vrc_irq::VrcIrq;
+
+/// How a VRC2/VRC4 board connects CPU address lines to the chip's register select pins.
+#[derive(Clone, Copy, Debug)]
+struct Wiring {
+    /// CPU address bits driving register bit 0 and bit 1
+    a0: u16,
+    a1: u16,
+    vrc2: bool,
+    /// VRC2a leaves out the low bit of the CHR bank numbers
+    char_shift: u8,
+}
+
+/// Konami VRC2 and VRC4, iNES mappers 21, 22, 23 and 25.
+///
+/// The mapper numbers only tell which address lines a board uses for the
+/// register select. The NES 2.0 submapper picks the exact variant, without one
+/// both possible lines are listened to and the board is treated as a VRC4,
+/// which is a superset of the VRC2 as far as games can tell.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_021 {
+    prog_banks: u8,
+    char_banks: u8,
+    wiring: Wiring,
+
+    prog_bank_0: u8,
+    prog_bank_1: u8,
+    prog_swap: bool,
+    char_bank: [u16; 8],
+    mirror: Mirror,
+    /// The one bit latch VRC2 boards have at $6000-$6FFF
+    microwire: u8,
+
+    irq: VrcIrq,
+}
+
+impl INES_021 {
+    pub fn new(mapper_id: u8, submapper: u8, prog_banks: u8, char_banks: u8) -> Self {
+        let wiring = |a0, a1, vrc2| Wiring {
+            a0,
+            a1,
+            vrc2,
+            char_shift: 0,
+        };
+
+        let wiring = match (mapper_id, submapper) {
+            (21, 1) => wiring(0x02, 0x04, false),
+            (21, 2) => wiring(0x40, 0x80, false),
+            (21, _) => wiring(0x42, 0x84, false),
+            (22, _) => Wiring {
+                char_shift: 1,
+                ..wiring(0x02, 0x01, true)
+            },
+            (23, 1) => wiring(0x01, 0x02, false),
+            (23, 2) => wiring(0x04, 0x08, false),
+            (23, 3) => wiring(0x01, 0x02, true),
+            (23, _) => wiring(0x05, 0x0A, false),
+            (25, 1) => wiring(0x02, 0x01, false),
+            (25, 2) => wiring(0x08, 0x04, false),
+            (25, 3) => wiring(0x02, 0x01, true),
+            _ => wiring(0x0A, 0x05, false),
+        };
+
+        Self {
+            prog_banks,
+            char_banks,
+            wiring,
+
+            prog_bank_0: 0x00,
+            prog_bank_1: 0x01,
+            prog_swap: false,
+            char_bank: [0x00; 8],
+            mirror: Mirror::Vertical,
+            microwire: 0x00,
+
+            irq: VrcIrq::default(),
+        }
+    }
+
+    /// Folds the board's address lines back into $x000-$x003.
+    fn register(&self, address: u16) -> u16 {
+        let a0 = (address & self.wiring.a0 != 0) as u16;
+        let a1 = (address & self.wiring.a1 != 0) as u16;
+
+        (address & 0xF000) | (a1 << 1) | a0
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        let bank = (self.char_bank[(address >> 10) as usize] >> self.wiring.char_shift) as usize;
+        let banks = (self.char_banks as usize * 8).max(8);
+
+        (bank % banks) * 0x0400 + (address & 0x03FF) as usize
+    }
+
+    fn write_char_bank(&mut self, register: u16, data: u8) {
+        // Two registers per 1K bank, each holding a nibble
+        let index = (((register >> 12) - 0x0B) * 2 + ((register >> 1) & 0x01)) as usize;
+        let bank = self.char_bank[index];
+
+        self.char_bank[index] = if register & 0x01 == 0 {
+            (bank & 0x1F0) | (data & 0x0F) as u16
+        } else {
+            let high = if self.wiring.vrc2 { 0x0F } else { 0x1F };
+            (bank & 0x0F) | (((data & high) as u16) << 4)
+        };
+    }
+}
+
+impl Mapper for INES_021 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let banks = (self.prog_banks as usize * 2).max(2);
+        let second_last = banks - 2;
+
+        let bank = match address {
+            0x6000..=0x6FFF if self.wiring.vrc2 => {
+                return Some(MappedAddress::Data(0x60 | self.microwire))
+            }
+            0x6000..=0x7FFF => return Some(MappedAddress::Ram((address & 0x1FFF) as usize)),
+            0x8000..=0x9FFF if self.prog_swap => second_last,
+            0x8000..=0x9FFF => self.prog_bank_0 as usize,
+            0xA000..=0xBFFF => self.prog_bank_1 as usize,
+            0xC000..=0xDFFF if self.prog_swap => self.prog_bank_0 as usize,
+            0xC000..=0xDFFF => second_last,
+            0xE000..=0xFFFF => banks - 1,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            (bank % banks) * 0x2000 + (address & 0x1FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x6FFF if self.wiring.vrc2 => self.microwire = data & 0x01,
+            0x6000..=0x7FFF if !self.wiring.vrc2 => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize));
+            }
+            0x8000..=0xFFFF => {
+                let register = self.register(address);
+
+                match register {
+                    0x8000..=0x8003 => self.prog_bank_0 = data & 0x1F,
+                    0x9000..=0x9003 if self.wiring.vrc2 => {
+                        self.mirror = if data & 0x01 == 0 {
+                            Mirror::Vertical
+                        } else {
+                            Mirror::Horizontal
+                        };
+                    }
+                    0x9000 => {
+                        self.mirror = match data & 0x03 {
+                            0 => Mirror::Vertical,
+                            1 => Mirror::Horizontal,
+                            2 => Mirror::OnescreenLo,
+                            _ => Mirror::OnescreenHi,
+                        };
+                    }
+                    0x9002 => self.prog_swap = data & 0x02 != 0,
+                    0xA000..=0xA003 => self.prog_bank_1 = data & 0x1F,
+                    0xB000..=0xEFFF => self.write_char_bank(register, data),
+                    0xF000 if !self.wiring.vrc2 => self.irq.write_latch_low(data),
+                    0xF001 if !self.wiring.vrc2 => self.irq.write_latch_high(data),
+                    0xF002 if !self.wiring.vrc2 => self.irq.write_control(data),
+                    0xF003 if !self.wiring.vrc2 => self.irq.acknowledge(),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.active()
+    }
+}
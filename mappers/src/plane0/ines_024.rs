@@ -0,0 +1,276 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+use super::vrc_irq::VrcIrq;
+
+/// Mix level of one step of the VRC6 channels, its pulses line up with the 2A03's
+const VRC6_LEVEL: f32 = 0.01;
+
+#[derive(Debug, Default)]
+struct Vrc6Pulse {
+    volume: u8,
+    duty: u8,
+    /// Ignores the duty and outputs the volume constantly
+    digitized: bool,
+    enabled: bool,
+    period: u16,
+    timer: u16,
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.digitized = data & 0x80 != 0;
+                self.duty = (data >> 4) & 0x07;
+                self.volume = data & 0x0F;
+            }
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data & 0x0F) as u16) << 8;
+                self.enabled = data & 0x80 != 0;
+
+                if !self.enabled {
+                    self.step = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = (self.step + 1) & 0x0F;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.digitized || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Vrc6Saw {
+    rate: u8,
+    enabled: bool,
+    period: u16,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Saw {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0x3F,
+            1 => self.period = (self.period & 0x0F00) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data & 0x0F) as u16) << 8;
+                self.enabled = data & 0x80 != 0;
+
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period >> shift;
+        self.step += 1;
+
+        // The rate gets added on every second clock, the 14th starts over
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step.is_multiple_of(2) {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+/// Konami VRC6, iNES mappers 24 (VRC6a) and 26 (VRC6b, register lines A0 and A1 swapped).
+///
+/// Besides the banking and the usual VRC IRQ the chip has two pulse channels
+/// and a sawtooth, which get mixed with the APU output.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_024 {
+    prog_banks: u8,
+    char_banks: u8,
+    swapped: bool,
+
+    prog_bank_16: u8,
+    prog_bank_8: u8,
+    char_bank: [u8; 8],
+    banking: u8,
+
+    irq: VrcIrq,
+
+    /// $9003: halt, then the 16x and 256x frequency boosts
+    frequency_control: u8,
+    pulse1: Vrc6Pulse,
+    pulse2: Vrc6Pulse,
+    saw: Vrc6Saw,
+}
+
+impl INES_024 {
+    pub fn new(mapper_id: u8, prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+            swapped: mapper_id == 26,
+
+            prog_bank_16: 0x00,
+            prog_bank_8: 0x00,
+            char_bank: [0x00; 8],
+            banking: 0x00,
+
+            irq: VrcIrq::default(),
+
+            frequency_control: 0x00,
+            pulse1: Vrc6Pulse::default(),
+            pulse2: Vrc6Pulse::default(),
+            saw: Vrc6Saw::default(),
+        }
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        let slot = (address >> 10) as usize;
+        let bank = match self.banking & 0x03 {
+            0 => self.char_bank[slot] as usize,
+            1 => self.char_bank[slot >> 1] as usize * 2 + (slot & 0x01),
+            _ if slot < 4 => self.char_bank[slot] as usize,
+            _ => self.char_bank[4 + ((slot - 4) >> 1)] as usize * 2 + (slot & 0x01),
+        };
+        let banks = (self.char_banks as usize * 8).max(8);
+
+        (bank % banks) * 0x0400 + (address & 0x03FF) as usize
+    }
+
+    fn prog_ram_enabled(&self) -> bool {
+        self.banking & 0x80 != 0
+    }
+}
+
+impl Mapper for INES_024 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let banks = (self.prog_banks as usize * 2).max(2);
+
+        let bank = match address {
+            0x6000..=0x7FFF if self.prog_ram_enabled() => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize))
+            }
+            0x8000..=0xBFFF => self.prog_bank_16 as usize * 2 + ((address >> 13) & 0x01) as usize,
+            0xC000..=0xDFFF => self.prog_bank_8 as usize,
+            0xE000..=0xFFFF => banks - 1,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            (bank % banks) * 0x2000 + (address & 0x1FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        if (0x6000..=0x7FFF).contains(&address) {
+            return self
+                .prog_ram_enabled()
+                .then_some(MappedAddress::Ram((address & 0x1FFF) as usize));
+        }
+
+        let address = if self.swapped {
+            (address & 0xFFFC) | ((address & 0x01) << 1) | ((address >> 1) & 0x01)
+        } else {
+            address
+        };
+
+        let register = address & 0x0003;
+
+        match address & 0xF003 {
+            0x8000..=0x8003 => self.prog_bank_16 = data & 0x0F,
+            0x9000..=0x9002 => self.pulse1.write(register, data),
+            0x9003 => self.frequency_control = data & 0x07,
+            0xA000..=0xA002 => self.pulse2.write(register, data),
+            0xB000..=0xB002 => self.saw.write(register, data),
+            0xB003 => self.banking = data,
+            0xC000..=0xC003 => self.prog_bank_8 = data & 0x1F,
+            0xD000..=0xD003 => self.char_bank[register as usize] = data,
+            0xE000..=0xE003 => self.char_bank[4 + register as usize] = data,
+            0xF000 => self.irq.write_latch(data),
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(match (self.banking >> 2) & 0x03 {
+            0 => Mirror::Vertical,
+            1 => Mirror::Horizontal,
+            2 => Mirror::OnescreenLo,
+            _ => Mirror::OnescreenHi,
+        })
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+
+        if self.frequency_control & 0x01 == 0 {
+            let shift = if self.frequency_control & 0x04 != 0 {
+                8
+            } else if self.frequency_control & 0x02 != 0 {
+                4
+            } else {
+                0
+            };
+
+            self.pulse1.clock(shift);
+            self.pulse2.clock(shift);
+            self.saw.clock(shift);
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.active()
+    }
+
+    fn audio_output(&self) -> f32 {
+        let level = self.pulse1.output() + self.pulse2.output() + self.saw.output();
+
+        level as f32 * VRC6_LEVEL
+    }
+}
@@ -0,0 +1,104 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper};
+
+/// Two unrelated boards share this number:
+/// BNROM, CHR-RAM with a 32K PRG bank latched by writes to $8000-$FFFF, and
+/// NINA-001, CHR-ROM with registers at $7FFD-$7FFF sitting on top of its PRG-RAM.
+///
+/// NES 2.0 tells them apart with submapper 1 (NINA-001) and 2 (BNROM). iNES
+/// headers can't, so those fall back to guessing by the CHR size, which gets
+/// NINA-001 boards with CHR-RAM wrong.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_034 {
+    prog_banks: u8,
+    char_banks: u8,
+    nina: bool,
+    prog_bank: u8,
+    char_bank_0: u8,
+    char_bank_1: u8,
+}
+
+impl INES_034 {
+    pub fn new(submapper: u8, prog_banks: u8, char_banks: u8) -> Self {
+        let nina = match submapper {
+            1 => true,
+            2 => false,
+            // BNROM never has more than 8K of CHR
+            _ => char_banks > 1,
+        };
+
+        Self {
+            prog_banks,
+            char_banks,
+            nina,
+            prog_bank: 0x00,
+            char_bank_0: 0x00,
+            char_bank_1: 0x01,
+        }
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        if !self.nina {
+            return address as usize;
+        }
+
+        let bank = if address < 0x1000 {
+            self.char_bank_0
+        } else {
+            self.char_bank_1
+        };
+
+        (bank as usize % (self.char_banks as usize * 2).max(2)) * 0x1000
+            + (address & 0x0FFF) as usize
+    }
+}
+
+impl Mapper for INES_034 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF if self.nina => Some(MappedAddress::Ram((address & 0x1FFF) as usize)),
+            0x8000..=0xFFFF => {
+                let banks = (self.prog_banks as usize / 2).max(1);
+                let bank = self.prog_bank as usize % banks;
+
+                Some(MappedAddress::Prog(
+                    bank * 0x8000 + (address & 0x7FFF) as usize,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF if self.nina => {
+                match address {
+                    0x7FFD => self.prog_bank = data & 0x01,
+                    0x7FFE => self.char_bank_0 = data & 0x0F,
+                    0x7FFF => self.char_bank_1 = data & 0x0F,
+                    _ => {}
+                }
+
+                // The registers don't stop the write from reaching the RAM below
+                Some(MappedAddress::Ram((address & 0x1FFF) as usize))
+            }
+            0x8000..=0xFFFF if !self.nina => {
+                self.prog_bank = data;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn bus_conflicts(&self) -> bool {
+        !self.nina
+    }
+}
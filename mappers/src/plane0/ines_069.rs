@@ -0,0 +1,318 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+
+/// Mix level of a 5B channel at full volume, about a 2A03 pulse channel at full volume
+const SUNSOFT_5B_LEVEL: f32 = 0.15;
+
+/// The YM2149 derived sound core of the Sunsoft 5B: three square channels
+/// sharing a noise generator and an envelope generator, with logarithmic volume.
+#[derive(Debug)]
+struct Sunsoft5B {
+    registers: [u8; 16],
+    /// Indexed by the 5 bit envelope level, 1.5 dB apart
+    levels: [f32; 32],
+
+    /// Everything inside runs off CPU cycles divided by 16
+    prescaler: u8,
+    tone_counter: [u16; 3],
+    tone_output: [bool; 3],
+    noise_counter: u8,
+    noise_shift: u32,
+    envelope_counter: u16,
+    envelope_step: u8,
+    envelope_attack: bool,
+    envelope_holding: bool,
+}
+
+impl Default for Sunsoft5B {
+    fn default() -> Self {
+        let mut levels = [0.0; 32];
+        for (i, level) in levels.iter_mut().enumerate().skip(1) {
+            *level = 10.0_f32.powf((i as f32 - 31.0) * 1.5 / 20.0);
+        }
+
+        Self {
+            registers: [0x00; 16],
+            levels,
+
+            prescaler: 0,
+            tone_counter: [0; 3],
+            tone_output: [false; 3],
+            noise_counter: 0,
+            noise_shift: 0x0001,
+            envelope_counter: 0,
+            envelope_step: 0,
+            envelope_attack: false,
+            envelope_holding: false,
+        }
+    }
+}
+
+impl Sunsoft5B {
+    fn write(&mut self, register: u8, data: u8) {
+        let register = (register & 0x0F) as usize;
+        self.registers[register] = data;
+
+        // Writing the shape restarts the envelope
+        if register == 0x0D {
+            self.envelope_step = 0;
+            self.envelope_counter = 0;
+            self.envelope_attack = data & 0x04 != 0;
+            self.envelope_holding = false;
+        }
+    }
+
+    fn tone_period(&self, channel: usize) -> u16 {
+        let period = self.registers[channel * 2] as u16
+            | ((self.registers[channel * 2 + 1] & 0x0F) as u16) << 8;
+
+        period.max(1)
+    }
+
+    fn envelope_level(&self) -> u8 {
+        if self.envelope_attack {
+            self.envelope_step
+        } else {
+            31 - self.envelope_step
+        }
+    }
+
+    fn clock_envelope(&mut self) {
+        if self.envelope_holding {
+            return;
+        }
+
+        if self.envelope_step < 31 {
+            self.envelope_step += 1;
+            return;
+        }
+
+        let shape = self.registers[0x0D];
+
+        // Without continue the envelope drops to 0 and stays there
+        if shape & 0x08 == 0 {
+            self.envelope_attack = false;
+            self.envelope_holding = true;
+            return;
+        }
+
+        if shape & 0x02 != 0 {
+            self.envelope_attack = !self.envelope_attack;
+        }
+
+        if shape & 0x01 != 0 {
+            self.envelope_holding = true;
+        } else {
+            self.envelope_step = 0;
+        }
+    }
+
+    fn clock(&mut self) {
+        self.prescaler += 1;
+        if self.prescaler < 16 {
+            return;
+        }
+        self.prescaler = 0;
+
+        for channel in 0..3 {
+            self.tone_counter[channel] += 1;
+
+            if self.tone_counter[channel] >= self.tone_period(channel) {
+                self.tone_counter[channel] = 0;
+                self.tone_output[channel] = !self.tone_output[channel];
+            }
+        }
+
+        // The noise runs at half the rate of the tones
+        self.noise_counter += 1;
+        if self.noise_counter >= (self.registers[0x06] & 0x1F).max(1) * 2 {
+            self.noise_counter = 0;
+
+            let feedback = (self.noise_shift ^ (self.noise_shift >> 3)) & 0x01;
+            self.noise_shift = (self.noise_shift >> 1) | (feedback << 16);
+        }
+
+        self.envelope_counter += 1;
+        let envelope_period = self.registers[0x0B] as u16 | (self.registers[0x0C] as u16) << 8;
+        if self.envelope_counter >= envelope_period.max(1) {
+            self.envelope_counter = 0;
+            self.clock_envelope();
+        }
+    }
+
+    fn output(&self) -> f32 {
+        let mixer = self.registers[0x07];
+        let noise = self.noise_shift & 0x01 != 0;
+
+        (0..3)
+            .map(|channel| {
+                let tone_on = self.tone_output[channel] || mixer & (0x01 << channel) != 0;
+                let noise_on = noise || mixer & (0x08 << channel) != 0;
+
+                if !(tone_on && noise_on) {
+                    return 0.0;
+                }
+
+                let volume = self.registers[0x08 + channel];
+                let level = if volume & 0x10 != 0 {
+                    self.envelope_level()
+                } else if volume & 0x0F == 0 {
+                    0
+                } else {
+                    // The 4 bit volume steps 3 dB, two envelope steps
+                    (volume & 0x0F) * 2 + 1
+                };
+
+                self.levels[level as usize]
+            })
+            .sum()
+    }
+}
+
+/// Sunsoft FME-7 and 5B.
+///
+/// Everything goes through a command register at $8000 and a parameter
+/// register at $A000. The 5B adds its audio registers at $C000/$E000.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_069 {
+    prog_banks: u8,
+    char_banks: u8,
+
+    command: u8,
+    char_bank: [u8; 8],
+    /// $6000, $8000, $A000 and $C000
+    prog_bank: [u8; 4],
+    mirror: Mirror,
+
+    irq_enabled: bool,
+    irq_counter_enabled: bool,
+    irq_counter: u16,
+    irq_active: bool,
+
+    audio_register: u8,
+    audio: Sunsoft5B,
+}
+
+impl INES_069 {
+    pub fn new(prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+
+            command: 0x00,
+            char_bank: [0x00; 8],
+            prog_bank: [0x00; 4],
+            mirror: Mirror::Vertical,
+
+            irq_enabled: false,
+            irq_counter_enabled: false,
+            irq_counter: 0x0000,
+            irq_active: false,
+
+            audio_register: 0x00,
+            audio: Sunsoft5B::default(),
+        }
+    }
+
+    fn write_parameter(&mut self, data: u8) {
+        match self.command {
+            0x00..=0x07 => self.char_bank[self.command as usize] = data,
+            0x08 => self.prog_bank[0] = data,
+            0x09..=0x0B => self.prog_bank[(self.command - 0x08) as usize] = data & 0x3F,
+            0x0C => {
+                self.mirror = match data & 0x03 {
+                    0 => Mirror::Vertical,
+                    1 => Mirror::Horizontal,
+                    2 => Mirror::OnescreenLo,
+                    _ => Mirror::OnescreenHi,
+                };
+            }
+            0x0D => {
+                self.irq_enabled = data & 0x01 != 0;
+                self.irq_counter_enabled = data & 0x80 != 0;
+                self.irq_active = false;
+            }
+            0x0E => self.irq_counter = (self.irq_counter & 0xFF00) | data as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | (data as u16) << 8,
+        }
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        let bank = self.char_bank[(address >> 10) as usize] as usize;
+        let banks = (self.char_banks as usize * 8).max(8);
+
+        (bank % banks) * 0x0400 + (address & 0x03FF) as usize
+    }
+}
+
+impl Mapper for INES_069 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let banks = (self.prog_banks as usize * 2).max(1);
+
+        let bank = match address {
+            // Bit 6 picks RAM over ROM, bit 7 enables the RAM
+            0x6000..=0x7FFF => match self.prog_bank[0] & 0xC0 {
+                0xC0 => return Some(MappedAddress::Ram((address & 0x1FFF) as usize)),
+                0x40 => return None,
+                _ => (self.prog_bank[0] & 0x3F) as usize,
+            },
+            0x8000..=0xDFFF => self.prog_bank[((address - 0x6000) >> 13) as usize] as usize,
+            0xE000..=0xFFFF => banks - 1,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            (bank % banks) * 0x2000 + (address & 0x1FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        match address {
+            0x6000..=0x7FFF if self.prog_bank[0] & 0xC0 == 0xC0 => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize));
+            }
+            0x8000..=0x9FFF => self.command = data & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(data),
+            0xC000..=0xDFFF => self.audio_register = data,
+            // The 5B ignores selects with the upper bits set
+            0xE000..=0xFFFF if self.audio_register & 0xF0 == 0 => {
+                self.audio.write(self.audio_register, data)
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(self.mirror.clone())
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.irq_counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+
+            if self.irq_counter == 0xFFFF && self.irq_enabled {
+                self.irq_active = true;
+            }
+        }
+
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_active
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output() * SUNSOFT_5B_LEVEL
+    }
+}
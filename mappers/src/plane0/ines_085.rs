@@ -0,0 +1,517 @@
+use super::super::{MappedAddress, MappedPpuAddress, Mapper, Mirror};
+use super::vrc_irq::VrcIrq;
+
+/// Mix level of a channel's carrier at full amplitude
+const VRC7_LEVEL: f32 = 0.1;
+/// CPU cycles per OPLL sample, the chip runs off twice the CPU clock divided by 72
+const VRC7_SAMPLE_CYCLES: u8 = 36;
+
+/// The built-in instruments 1-15, laid out like the custom instrument at $00-$07
+const VRC7_PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+/// Frequency multipliers, doubled so 1/2 stays an integer
+const MULTIPLIERS: [u32; 16] = [1, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 20, 24, 24, 30, 30];
+/// Key scale level attenuation of an octave by the top 4 F-number bits, in 0.375 dB steps
+const KEY_SCALE_LEVELS: [i16; 16] = [
+    0, 24, 32, 37, 40, 43, 45, 47, 48, 50, 51, 52, 53, 54, 55, 56,
+];
+/// Which of 8 envelope clocks advance the envelope, by the low 2 bits of the rate
+const ENVELOPE_STEPS: [[u32; 8]; 4] = [
+    [0, 1, 0, 1, 0, 1, 0, 1],
+    [0, 1, 0, 1, 1, 1, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 1],
+];
+/// Vibrato offset in eighths of the top 3 F-number bits
+const VIBRATO: [i32; 8] = [0, 1, 2, 1, 0, -1, -2, -1];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// One FM operator, every channel has a modulator (0) and a carrier (1).
+#[derive(Clone, Copy, Debug)]
+struct Operator {
+    /// 19 bits, the top 10 index the sine table
+    phase: u32,
+    /// Attenuation in 0.375 dB steps, 127 is silent
+    envelope: u8,
+    state: EnvelopeState,
+    /// The last two outputs, the modulator feeds them back into itself
+    output: [f32; 2],
+}
+
+impl Default for Operator {
+    fn default() -> Self {
+        Self {
+            phase: 0,
+            envelope: 127,
+            state: EnvelopeState::Release,
+            output: [0.0; 2],
+        }
+    }
+}
+
+impl Operator {
+    fn key_on(&mut self) {
+        self.phase = 0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        self.state = EnvelopeState::Release;
+    }
+
+    fn clock_envelope(
+        &mut self,
+        patch: &[u8; 8],
+        op: usize,
+        key_scale: u8,
+        sustain: bool,
+        clock: u32,
+    ) {
+        let sustained = patch[op] & 0x20 != 0;
+
+        let rate = match self.state {
+            EnvelopeState::Attack => patch[4 + op] >> 4,
+            EnvelopeState::Decay => patch[4 + op] & 0x0F,
+            EnvelopeState::Sustain if sustained => 0,
+            EnvelopeState::Sustain => patch[6 + op] & 0x0F,
+            EnvelopeState::Release if sustain => 5,
+            EnvelopeState::Release if sustained => patch[6 + op] & 0x0F,
+            EnvelopeState::Release => 7,
+        };
+
+        // The attack is instant at the top rate
+        if self.state == EnvelopeState::Attack && rate == 15 {
+            self.envelope = 0;
+            self.state = EnvelopeState::Decay;
+            return;
+        }
+
+        if rate == 0 {
+            return;
+        }
+
+        let key_scale = if patch[op] & 0x10 != 0 {
+            key_scale
+        } else {
+            key_scale >> 2
+        };
+        let increment = envelope_increment((rate * 4 + key_scale).min(63), clock);
+
+        if increment == 0 {
+            return;
+        }
+
+        match self.state {
+            EnvelopeState::Attack => {
+                let step = ((self.envelope as u32 * increment) >> 3).max(1);
+                self.envelope = self.envelope.saturating_sub(step as u8);
+
+                if self.envelope == 0 {
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.envelope = (self.envelope + increment as u8).min(127);
+
+                // Sustain levels are 3 dB apart, the top one is silent
+                let level = match patch[6 + op] >> 4 {
+                    15 => 127,
+                    level => level * 8,
+                };
+                if self.envelope >= level {
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            _ => self.envelope = (self.envelope + increment as u8).min(127),
+        }
+    }
+}
+
+fn envelope_increment(rate: u8, clock: u32) -> u32 {
+    let high = rate >> 2;
+    let low = (rate & 0x03) as usize;
+
+    if high < 13 {
+        let shift = 13 - high;
+
+        if clock & ((1 << shift) - 1) != 0 {
+            return 0;
+        }
+
+        return ENVELOPE_STEPS[low][((clock >> shift) & 0x07) as usize];
+    }
+
+    ((1 << (high - 13)) * (1 + ENVELOPE_STEPS[low][(clock & 0x07) as usize])).min(4)
+}
+
+/// The YM2413 (OPLL) derived FM synth of the VRC7.
+///
+/// Six two operator channels, each playing the custom instrument or one of the
+/// 15 built in ones. Samples are made at the chip's own rate of about 49.7 kHz
+/// and held in between.
+#[derive(Debug)]
+struct Opll {
+    registers: [u8; 0x40],
+    operators: [[Operator; 2]; 6],
+    sine: Vec<f32>,
+    /// Linear gain for an attenuation in 0.375 dB steps
+    gains: Vec<f32>,
+
+    cycles: u8,
+    envelope_clock: u32,
+    /// Tremolo triangle position, 0-207
+    tremolo: u16,
+    vibrato: u16,
+    output: f32,
+}
+
+impl Default for Opll {
+    fn default() -> Self {
+        Self {
+            registers: [0x00; 0x40],
+            operators: [[Operator::default(); 2]; 6],
+            sine: (0..1024)
+                .map(|i| (i as f32 * std::f32::consts::TAU / 1024.0).sin())
+                .collect(),
+            gains: (0..512)
+                .map(|i| 10.0_f32.powf(i as f32 * -0.375 / 20.0))
+                .collect(),
+
+            cycles: 0,
+            envelope_clock: 0,
+            tremolo: 0,
+            vibrato: 0,
+            output: 0.0,
+        }
+    }
+}
+
+impl Opll {
+    fn write(&mut self, register: u8, data: u8) {
+        let register = (register & 0x3F) as usize;
+        let old = self.registers[register];
+        self.registers[register] = data;
+
+        // Key on and key off happen on the edge of bit 4
+        if let 0x20..=0x25 = register {
+            let operators = &mut self.operators[register - 0x20];
+
+            match (old & 0x10 != 0, data & 0x10 != 0) {
+                (false, true) => operators.iter_mut().for_each(Operator::key_on),
+                // Only the carrier is released, the modulator plays on
+                (true, false) => operators[1].key_off(),
+                _ => {}
+            }
+        }
+    }
+
+    fn patch(&self, channel: usize) -> [u8; 8] {
+        match self.registers[0x30 + channel] >> 4 {
+            0 => self.registers[..8].try_into().unwrap(),
+            instrument => VRC7_PATCHES[instrument as usize - 1],
+        }
+    }
+
+    fn operator_output(
+        &self,
+        operator: &Operator,
+        attenuation: i16,
+        phase: i32,
+        rectify: bool,
+    ) -> f32 {
+        let sample = self.sine[(phase & 0x3FF) as usize];
+
+        if rectify && sample < 0.0 {
+            return 0.0;
+        }
+
+        let attenuation = (operator.envelope as i16 + attenuation).clamp(0, 511);
+        sample * self.gains[attenuation as usize]
+    }
+
+    fn channel(&mut self, channel: usize) -> f32 {
+        let patch = self.patch(channel);
+        let control = self.registers[0x20 + channel];
+
+        let fnum = self.registers[0x10 + channel] as i32 | ((control & 0x01) as i32) << 8;
+        let block = (control >> 1) & 0x07;
+        let sustain = control & 0x20 != 0;
+        let key_scale = (block << 1) | (fnum >> 8) as u8;
+
+        let tremolo = if self.tremolo < 104 {
+            self.tremolo / 8
+        } else {
+            (207 - self.tremolo) / 8
+        } as i16;
+        let vibrato = (fnum >> 6) * VIBRATO[(self.vibrato >> 10) as usize & 0x07] / 2;
+
+        // Attenuation from the key scale level, 6 dB per octave at the steepest
+        let key_scale_level =
+            (KEY_SCALE_LEVELS[(fnum >> 5) as usize] - 16 * (7 - block as i16)).max(0);
+
+        let mut modulation = 0.0;
+        let mut output = 0.0;
+
+        for op in 0..2 {
+            let mut operator = self.operators[channel][op];
+            operator.clock_envelope(&patch, op, key_scale, sustain, self.envelope_clock);
+
+            let op_fnum = if patch[op] & 0x40 != 0 {
+                fnum + vibrato
+            } else {
+                fnum
+            };
+            let increment =
+                ((op_fnum.max(0) as u32 * MULTIPLIERS[(patch[op] & 0x0F) as usize]) << block) >> 1;
+            operator.phase = (operator.phase + increment) & 0x7FFFF;
+
+            let mut attenuation = match (patch[2 + op] >> 6) & 0x03 {
+                0 => 0,
+                1 => key_scale_level >> 2,
+                2 => key_scale_level >> 1,
+                _ => key_scale_level,
+            };
+            if patch[op] & 0x80 != 0 {
+                attenuation += tremolo;
+            }
+
+            let phase = (operator.phase >> 9) as i32;
+
+            if op == 0 {
+                // Total level in 0.75 dB steps
+                attenuation += (patch[2] & 0x3F) as i16 * 2;
+
+                let feedback = match patch[3] & 0x07 {
+                    0 => 0,
+                    feedback => {
+                        let average = (operator.output[0] + operator.output[1]) / 2.0;
+                        (average * 1024.0 / (1 << (7 - feedback)) as f32) as i32
+                    }
+                };
+
+                let out = self.operator_output(
+                    &operator,
+                    attenuation,
+                    phase + feedback,
+                    patch[3] & 0x08 != 0,
+                );
+                operator.output = [operator.output[1], out];
+                modulation = out;
+            } else {
+                // Volume in 3 dB steps
+                attenuation += (self.registers[0x30 + channel] & 0x0F) as i16 * 8;
+
+                let phase = phase + (modulation * 2048.0) as i32;
+                output = self.operator_output(&operator, attenuation, phase, patch[3] & 0x10 != 0);
+            }
+
+            self.operators[channel][op] = operator;
+        }
+
+        output
+    }
+
+    fn clock(&mut self) {
+        self.cycles += 1;
+        if self.cycles < VRC7_SAMPLE_CYCLES {
+            return;
+        }
+        self.cycles = 0;
+
+        self.envelope_clock = self.envelope_clock.wrapping_add(1);
+
+        // Tremolo at about 3.7 Hz, vibrato at about 6.1 Hz
+        if self.envelope_clock.is_multiple_of(64) {
+            self.tremolo = (self.tremolo + 1) % 208;
+        }
+        self.vibrato = (self.vibrato + 1) & 0x1FFF;
+
+        self.output = (0..6).map(|channel| self.channel(channel)).sum();
+    }
+}
+
+/// Konami VRC7, iNES mapper 85.
+///
+/// VRC7a boards select registers with A4 and VRC7b boards with A3, the NES 2.0
+/// submapper picks one and without it both are listened to. Only the VRC7a
+/// boards have the FM synth hooked up, played through $9010 and $9030.
+#[allow(non_camel_case_types)]
+#[derive(Debug)]
+pub struct INES_085 {
+    prog_banks: u8,
+    char_banks: u8,
+    /// CPU address bits selecting the second register of a pair
+    select: u16,
+
+    prog_bank: [u8; 3],
+    char_bank: [u8; 8],
+    /// $E000: mirroring, sound reset and PRG-RAM enable
+    control: u8,
+
+    irq: VrcIrq,
+
+    audio_register: u8,
+    audio: Opll,
+}
+
+impl INES_085 {
+    pub fn new(submapper: u8, prog_banks: u8, char_banks: u8) -> Self {
+        Self {
+            prog_banks,
+            char_banks,
+            select: match submapper {
+                1 => 0x08,
+                2 => 0x10,
+                _ => 0x18,
+            },
+
+            prog_bank: [0x00; 3],
+            char_bank: [0x00; 8],
+            control: 0x00,
+
+            irq: VrcIrq::default(),
+
+            audio_register: 0x00,
+            audio: Opll::default(),
+        }
+    }
+
+    fn char_offset(&self, address: u16) -> usize {
+        let bank = self.char_bank[(address >> 10) as usize] as usize;
+        let banks = (self.char_banks as usize * 8).max(8);
+
+        (bank % banks) * 0x0400 + (address & 0x03FF) as usize
+    }
+
+    fn prog_ram_enabled(&self) -> bool {
+        self.control & 0x80 != 0
+    }
+
+    fn sound_reset(&self) -> bool {
+        self.control & 0x40 != 0
+    }
+}
+
+impl Mapper for INES_085 {
+    fn cpu_read(&mut self, address: u16) -> Option<MappedAddress> {
+        let banks = (self.prog_banks as usize * 2).max(1);
+
+        let bank = match address {
+            0x6000..=0x7FFF if self.prog_ram_enabled() => {
+                return Some(MappedAddress::Ram((address & 0x1FFF) as usize))
+            }
+            0x8000..=0xDFFF => self.prog_bank[((address - 0x8000) >> 13) as usize] as usize,
+            0xE000..=0xFFFF => banks - 1,
+            _ => return None,
+        };
+
+        Some(MappedAddress::Prog(
+            (bank % banks) * 0x2000 + (address & 0x1FFF) as usize,
+        ))
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) -> Option<MappedAddress> {
+        if (0x6000..=0x7FFF).contains(&address) {
+            return self
+                .prog_ram_enabled()
+                .then_some(MappedAddress::Ram((address & 0x1FFF) as usize));
+        }
+
+        match address & 0xF030 {
+            0x9010 => {
+                self.audio_register = data;
+                return None;
+            }
+            0x9030 => {
+                self.audio.write(self.audio_register, data);
+                return None;
+            }
+            _ => {}
+        }
+
+        let register = (address & 0xF000)
+            | if address & self.select != 0 {
+                0x10
+            } else {
+                0x00
+            };
+
+        match register {
+            0x8000 => self.prog_bank[0] = data & 0x3F,
+            0x8010 => self.prog_bank[1] = data & 0x3F,
+            0x9000 => self.prog_bank[2] = data & 0x3F,
+            0xA000..=0xD010 => {
+                let index =
+                    ((register - 0xA000) >> 11) as usize | ((register >> 4) & 0x01) as usize;
+                self.char_bank[index] = data;
+            }
+            0xE000 => self.control = data,
+            0xE010 => self.irq.write_latch(data),
+            0xF000 => self.irq.write_control(data),
+            0xF010 => self.irq.acknowledge(),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn ppu_read(&mut self, address: u16) -> Option<MappedPpuAddress> {
+        if address <= 0x1FFF {
+            return Some(MappedPpuAddress::Chr(self.char_offset(address)));
+        }
+
+        None
+    }
+
+    fn mirror(&self) -> Option<Mirror> {
+        Some(match self.control & 0x03 {
+            0 => Mirror::Vertical,
+            1 => Mirror::Horizontal,
+            2 => Mirror::OnescreenLo,
+            _ => Mirror::OnescreenHi,
+        })
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+
+        if !self.sound_reset() {
+            self.audio.clock();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.active()
+    }
+
+    fn audio_output(&self) -> f32 {
+        if self.sound_reset() {
+            return 0.0;
+        }
+
+        self.audio.output * VRC7_LEVEL
+    }
+}
@@ -1 +1,22 @@
 pub mod ines_000;
+pub mod ines_001;
+pub mod ines_002;
+pub mod ines_003;
+pub mod ines_004;
+pub mod ines_005;
+pub mod ines_007;
+pub mod ines_009;
+pub mod ines_010;
+pub mod ines_011;
+pub mod ines_016;
+pub mod ines_019;
+pub mod ines_021;
+pub mod ines_024;
+pub mod ines_034;
+pub mod ines_066;
+pub mod ines_069;
+pub mod ines_085;
+
+pub(crate) mod chr_latch;
+pub(crate) mod i2c_eeprom;
+pub(crate) mod vrc_irq;
@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+
+use bitflags::bitflags;
+
+use crate::prelude::*;
+
+bitflags! {
+    /// What a board does beyond plain PRG and CHR banking.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct MapperFeatures: u16 {
+        /// PRG-RAM at $6000-$7FFF, battery backed when the header says so
+        const PRG_RAM = 0b0000_0001;
+        /// Raises IRQs on its own
+        const IRQ = 0b0000_0010;
+        /// Sound channels mixed into the APU output
+        const EXPANSION_AUDIO = 0b0000_0100;
+        /// Switches the nametable mirroring at runtime
+        const MIRROR_CONTROL = 0b0000_1000;
+        /// Maps nametables beyond what mirroring can express, e.g. to CHR or its own RAM
+        const NAMETABLE_MAPPING = 0b0001_0000;
+        /// Register writes get ANDed with the ROM byte at that address
+        const BUS_CONFLICTS = 0b0010_0000;
+        /// Memory inside the mapper that gets saved along with the PRG-RAM
+        const BATTERY_RAM = 0b0100_0000;
+    }
+}
+
+/// Everything from the cartridge header a mapper gets built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapperConfig {
+    pub mapper: u16,
+    pub submapper: u8,
+    /// Number of 16K PRG-ROM banks
+    pub prog_banks: u8,
+    /// Number of 8K CHR-ROM banks, 0 for CHR-RAM
+    pub char_banks: u8,
+    /// Number of 8K PRG-RAM banks
+    pub prog_ram_banks: u8,
+    /// The header's battery bit
+    pub battery: bool,
+}
+
+pub type MapperConstructor = fn(&MapperConfig) -> Box<dyn Mapper>;
+
+/// A mapper known to a [`MapperRegistry`].
+#[derive(Clone, Debug)]
+pub struct MapperInfo {
+    pub mapper: u16,
+    /// `None` handles every submapper that has no entry of its own
+    pub submapper: Option<u8>,
+    pub name: &'static str,
+    /// Names of the boards using this mapper
+    pub boards: &'static [&'static str],
+    pub features: MapperFeatures,
+    pub constructor: MapperConstructor,
+}
+
+/// Mappers by iNES/NES 2.0 mapper and submapper number.
+///
+/// `MapperRegistry::default()` knows every mapper in this crate. Boards from
+/// elsewhere can be added with [`MapperRegistry::register`], which also
+/// replaces a built-in one with the same numbers.
+#[derive(Clone, Debug)]
+pub struct MapperRegistry {
+    mappers: HashMap<(u16, Option<u8>), MapperInfo>,
+}
+
+impl MapperRegistry {
+    /// A registry without any mappers.
+    pub fn empty() -> Self {
+        Self {
+            mappers: HashMap::new(),
+        }
+    }
+
+    /// Adds a mapper, returning the one it replaced.
+    pub fn register(&mut self, info: MapperInfo) -> Option<MapperInfo> {
+        self.mappers.insert((info.mapper, info.submapper), info)
+    }
+
+    /// The entry for exactly this submapper, or else the one for the whole mapper.
+    pub fn get(&self, mapper: u16, submapper: u8) -> Option<&MapperInfo> {
+        self.mappers
+            .get(&(mapper, Some(submapper)))
+            .or_else(|| self.mappers.get(&(mapper, None)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MapperInfo> {
+        self.mappers.values()
+    }
+
+    /// Builds the mapper for `config`, `None` if it isn't registered.
+    pub fn create(&self, config: &MapperConfig) -> Option<Box<dyn Mapper>> {
+        let info = self.get(config.mapper, config.submapper)?;
+        Some((info.constructor)(config))
+    }
+}
+
+impl Default for MapperRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        for info in builtin() {
+            registry.register(info);
+        }
+
+        registry
+    }
+}
+
+fn builtin() -> Vec<MapperInfo> {
+    let info = |mapper, name, boards, features, constructor| MapperInfo {
+        mapper,
+        submapper: None,
+        name,
+        boards,
+        features,
+        constructor,
+    };
+
+    type F = MapperFeatures;
+
+    vec![
+        info(0, "NROM", &["NROM-128", "NROM-256"], F::empty(), |c| {
+            Box::new(INES_000::new(c.prog_banks, c.char_banks))
+        }),
+        info(
+            1,
+            "MMC1",
+            &["SAROM", "SKROM", "SLROM", "SNROM", "SUROM", "SXROM"],
+            F::PRG_RAM | F::MIRROR_CONTROL,
+            |c| Box::new(INES_001::new(c.prog_banks, c.char_banks, c.prog_ram_banks)),
+        ),
+        info(2, "UxROM", &["UNROM", "UOROM"], F::BUS_CONFLICTS, |c| {
+            Box::new(INES_002::new(c.prog_banks, c.char_banks))
+        }),
+        info(3, "CNROM", &["CNROM"], F::BUS_CONFLICTS, |c| {
+            Box::new(INES_003::new(c.prog_banks, c.char_banks))
+        }),
+        info(
+            4,
+            "MMC3",
+            &["TKROM", "TLROM", "TSROM", "TGROM", "TNROM"],
+            F::PRG_RAM | F::IRQ | F::MIRROR_CONTROL,
+            |c| Box::new(INES_004::new(c.prog_banks, c.char_banks)),
+        ),
+        info(
+            5,
+            "MMC5",
+            &["EKROM", "ELROM", "ETROM", "EWROM"],
+            F::PRG_RAM | F::IRQ | F::NAMETABLE_MAPPING,
+            |c| Box::new(INES_005::new(c.prog_banks, c.char_banks, c.prog_ram_banks)),
+        ),
+        info(
+            7,
+            "AxROM",
+            &["AMROM", "ANROM", "AOROM"],
+            F::MIRROR_CONTROL,
+            |c| Box::new(INES_007::new(c.prog_banks, c.char_banks)),
+        ),
+        info(9, "MMC2", &["PNROM", "PEEOROM"], F::MIRROR_CONTROL, |c| {
+            Box::new(INES_009::new(c.prog_banks, c.char_banks))
+        }),
+        info(
+            10,
+            "MMC4",
+            &["FJROM", "FKROM"],
+            F::PRG_RAM | F::MIRROR_CONTROL,
+            |c| Box::new(INES_010::new(c.prog_banks, c.char_banks)),
+        ),
+        info(
+            11,
+            "Color Dreams",
+            &["Color Dreams"],
+            F::BUS_CONFLICTS,
+            |c| Box::new(INES_011::new(c.prog_banks, c.char_banks)),
+        ),
+        info(
+            16,
+            "Bandai FCG",
+            &["FCG-1", "FCG-2", "LZ93D50"],
+            F::IRQ | F::MIRROR_CONTROL | F::BATTERY_RAM,
+            |c| {
+                Box::new(INES_016::new(
+                    16,
+                    c.submapper,
+                    c.battery,
+                    c.prog_banks,
+                    c.char_banks,
+                ))
+            },
+        ),
+        MapperInfo {
+            submapper: Some(4),
+            ..info(
+                16,
+                "Bandai FCG",
+                &["FCG-1", "FCG-2"],
+                F::IRQ | F::MIRROR_CONTROL,
+                |c| Box::new(INES_016::new(16, 4, c.battery, c.prog_banks, c.char_banks)),
+            )
+        },
+        MapperInfo {
+            submapper: Some(5),
+            ..info(
+                16,
+                "Bandai LZ93D50 with 24C02",
+                &["LZ93D50"],
+                F::IRQ | F::MIRROR_CONTROL | F::BATTERY_RAM,
+                |c| Box::new(INES_016::new(16, 5, c.battery, c.prog_banks, c.char_banks)),
+            )
+        },
+        info(
+            19,
+            "Namco 163",
+            &["Namco 163"],
+            F::PRG_RAM | F::IRQ | F::EXPANSION_AUDIO | F::NAMETABLE_MAPPING | F::BATTERY_RAM,
+            |c| Box::new(INES_019::new(c.prog_banks, c.char_banks)),
+        ),
+        vrc(21, "VRC4", &["VRC4a", "VRC4c"], F::PRG_RAM | F::IRQ),
+        vrc(22, "VRC2", &["VRC2a"], F::empty()),
+        vrc(
+            23,
+            "VRC2/VRC4",
+            &["VRC2b", "VRC4e", "VRC4f"],
+            F::PRG_RAM | F::IRQ,
+        ),
+        info(
+            24,
+            "VRC6",
+            &["VRC6a"],
+            F::PRG_RAM | F::IRQ | F::EXPANSION_AUDIO | F::MIRROR_CONTROL,
+            |c| Box::new(INES_024::new(c.mapper as u8, c.prog_banks, c.char_banks)),
+        ),
+        vrc(
+            25,
+            "VRC2/VRC4",
+            &["VRC2c", "VRC4b", "VRC4d"],
+            F::PRG_RAM | F::IRQ,
+        ),
+        info(
+            26,
+            "VRC6",
+            &["VRC6b"],
+            F::PRG_RAM | F::IRQ | F::EXPANSION_AUDIO | F::MIRROR_CONTROL,
+            |c| Box::new(INES_024::new(c.mapper as u8, c.prog_banks, c.char_banks)),
+        ),
+        info(
+            34,
+            "BNROM/NINA-001",
+            &["BNROM", "NINA-001"],
+            F::PRG_RAM | F::BUS_CONFLICTS,
+            |c| Box::new(INES_034::new(c.submapper, c.prog_banks, c.char_banks)),
+        ),
+        MapperInfo {
+            submapper: Some(1),
+            ..info(34, "NINA-001", &["NINA-001"], F::PRG_RAM, |c| {
+                Box::new(INES_034::new(1, c.prog_banks, c.char_banks))
+            })
+        },
+        MapperInfo {
+            submapper: Some(2),
+            ..info(34, "BNROM", &["BNROM"], F::BUS_CONFLICTS, |c| {
+                Box::new(INES_034::new(2, c.prog_banks, c.char_banks))
+            })
+        },
+        info(66, "GxROM", &["GNROM", "MHROM"], F::BUS_CONFLICTS, |c| {
+            Box::new(INES_066::new(c.prog_banks, c.char_banks))
+        }),
+        info(
+            69,
+            "Sunsoft FME-7",
+            &["JLROM", "JSROM", "Sunsoft 5B"],
+            F::PRG_RAM | F::IRQ | F::EXPANSION_AUDIO | F::MIRROR_CONTROL,
+            |c| Box::new(INES_069::new(c.prog_banks, c.char_banks)),
+        ),
+        info(
+            85,
+            "VRC7",
+            &["VRC7a", "VRC7b"],
+            F::PRG_RAM | F::IRQ | F::EXPANSION_AUDIO | F::MIRROR_CONTROL,
+            |c| Box::new(INES_085::new(c.submapper, c.prog_banks, c.char_banks)),
+        ),
+        info(
+            153,
+            "Bandai LZ93D50 with SRAM",
+            &["LZ93D50"],
+            F::PRG_RAM | F::IRQ | F::MIRROR_CONTROL,
+            |c| {
+                Box::new(INES_016::new(
+                    153,
+                    c.submapper,
+                    c.battery,
+                    c.prog_banks,
+                    c.char_banks,
+                ))
+            },
+        ),
+        info(
+            157,
+            "Bandai Datach",
+            &["Datach Joint ROM System"],
+            F::IRQ | F::MIRROR_CONTROL | F::BATTERY_RAM,
+            |c| {
+                Box::new(INES_016::new(
+                    157,
+                    c.submapper,
+                    c.battery,
+                    c.prog_banks,
+                    c.char_banks,
+                ))
+            },
+        ),
+        info(
+            159,
+            "Bandai LZ93D50 with 24C01",
+            &["LZ93D50"],
+            F::IRQ | F::MIRROR_CONTROL | F::BATTERY_RAM,
+            |c| {
+                Box::new(INES_016::new(
+                    159,
+                    c.submapper,
+                    c.battery,
+                    c.prog_banks,
+                    c.char_banks,
+                ))
+            },
+        ),
+    ]
+}
+
+/// VRC2 and VRC4 share one mapper type, which one a board is only shows in the wiring.
+fn vrc(
+    mapper: u16,
+    name: &'static str,
+    boards: &'static [&'static str],
+    features: MapperFeatures,
+) -> MapperInfo {
+    MapperInfo {
+        mapper,
+        submapper: None,
+        name,
+        boards,
+        features: features | MapperFeatures::MIRROR_CONTROL,
+        constructor: |c| {
+            Box::new(INES_021::new(
+                c.mapper as u8,
+                c.submapper,
+                c.prog_banks,
+                c.char_banks,
+            ))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mapper: u16, submapper: u8) -> MapperConfig {
+        MapperConfig {
+            mapper,
+            submapper,
+            prog_banks: 2,
+            char_banks: 1,
+            prog_ram_banks: 1,
+            battery: false,
+        }
+    }
+
+    #[test]
+    fn exact_submapper_wins() {
+        let registry = MapperRegistry::default();
+
+        let info = registry.get(16, 4).unwrap();
+        assert_eq!(info.submapper, Some(4));
+        assert_eq!(info.name, "Bandai FCG");
+
+        let info = registry.get(34, 2).unwrap();
+        assert_eq!(info.submapper, Some(2));
+        assert_eq!(info.name, "BNROM");
+    }
+
+    #[test]
+    fn falls_back_to_whole_mapper() {
+        let registry = MapperRegistry::default();
+
+        let info = registry.get(16, 7).unwrap();
+        assert_eq!((info.mapper, info.submapper), (16, None));
+
+        let info = registry.get(0, 3).unwrap();
+        assert_eq!((info.mapper, info.submapper), (0, None));
+        assert_eq!(info.name, "NROM");
+    }
+
+    #[test]
+    fn unknown_mapper() {
+        let registry = MapperRegistry::default();
+
+        assert!(registry.get(255, 0).is_none());
+        assert!(registry.create(&config(255, 0)).is_none());
+        assert!(MapperRegistry::empty().create(&config(0, 0)).is_none());
+    }
+
+    #[test]
+    fn registered_board_takes_its_submapper_only() {
+        let mut registry = MapperRegistry::default();
+
+        let custom = MapperInfo {
+            mapper: 0,
+            submapper: Some(1),
+            name: "Custom",
+            boards: &["CUSTOM"],
+            features: MapperFeatures::empty(),
+            constructor: |c| Box::new(INES_000::new(c.prog_banks, c.char_banks)),
+        };
+        assert!(registry.register(custom.clone()).is_none());
+        assert_eq!(registry.register(custom).unwrap().name, "Custom");
+
+        assert_eq!(registry.get(0, 1).unwrap().name, "Custom");
+        assert_eq!(registry.get(0, 0).unwrap().name, "NROM");
+        assert!(registry.create(&config(0, 1)).is_some());
+    }
+}
@@ -212,6 +212,16 @@ pub struct PPU {
     bg_shifter_attrib_lo: u16,
     bg_shifter_attrib_hi: u16,
 
+    oam: [u8; 256],
+    oam_addr: u8,
+    secondary_oam: [u8; 32],
+    sprite_count: u8,
+    spr_shifter_pattern_lo: [u8; 8],
+    spr_shifter_pattern_hi: [u8; 8],
+    spr_attrib: [u8; 8],
+    spr_x_counter: [u8; 8],
+    spr_zero_hit_possible: bool,
+
     pub log: Vec<String>,
 }
 
@@ -245,16 +255,47 @@ impl Default for PPU {
             bg_shifter_attrib_lo: 0x0000,
             bg_shifter_attrib_hi: 0x0000,
 
+            oam: [0xFF; 256],
+            oam_addr: 0x00,
+            secondary_oam: [0xFF; 32],
+            sprite_count: 0,
+            spr_shifter_pattern_lo: [0x00; 8],
+            spr_shifter_pattern_hi: [0x00; 8],
+            spr_attrib: [0x00; 8],
+            spr_x_counter: [0xFF; 8],
+            spr_zero_hit_possible: false,
+
             log: Vec::new(),
         }
     }
 }
 
+/// Address as the cartridge sees it, $3000-$3EFF mirror the nametables at $2000-$2EFF.
+fn cartridge_address(address: u16) -> u16 {
+    if address >= 0x2000 {
+        0x2000 | (address & 0x0FFF)
+    } else {
+        address
+    }
+}
+
 impl PPU {
     pub fn attach_cart(&mut self, cart: Rc<RefCell<cartridge::Cartridge>>) {
         self.cartridge = Some(cart);
     }
 
+    fn rendering_enabled(&self) -> bool {
+        self.mask.intersects(PPUMask::REND_BG | PPUMask::REND_SPR)
+    }
+
+    fn sprite_height(&self) -> i16 {
+        if self.control.contains(PPUControl::SPR_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
+
     pub fn get_color(&self, palette: u8, pixel: u8) -> u8 {
         self.ppu_read(0x3F00 + ((palette as u16) << 2) + (pixel as u16)) & 0x3F
     }
@@ -298,7 +339,16 @@ impl PPU {
                 data
             }
             0x0003 => 0x00,
-            0x0004 => 0x00,
+            0x0004 => {
+                let data = self.oam[self.oam_addr as usize];
+
+                // Bits 2-4 of the attribute byte don't exist in OAM
+                if self.oam_addr & 0x03 == 0x02 {
+                    data & 0xE3
+                } else {
+                    data
+                }
+            }
             0x0005 => 0x00,
             0x0006 => 0x00,
             0x0007 => {
@@ -336,8 +386,13 @@ impl PPU {
                 self.mask = PPUMask::from_bits_retain(data);
             }
             0x0002 => {}
-            0x0003 => {}
-            0x0004 => {}
+            0x0003 => {
+                self.oam_addr = data;
+            }
+            0x0004 => {
+                self.oam[self.oam_addr as usize] = data;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
             0x0005 => {
                 // println!("SCRL < {data:#06x} @ ({}, {})", self.cycle, self.scanline);
                 if self.address_latch == 0 {
@@ -381,9 +436,22 @@ impl PPU {
     pub fn ppu_read(&self, mut address: u16) -> u8 {
         address &= 0x3FFF;
 
-        if let Some(data) = self.cartridge.as_ref().unwrap().borrow().ppu_read(address) {
-            return data;
-        } else if (0x0000..=0x1FFF).contains(&address) {
+        // Palette reads stay inside the PPU, everything else shows up on the cartridge bus
+        // and the mapper gets the first say on where it lands
+        if address < 0x3F00 {
+            let mut cart = self.cartridge.as_ref().unwrap().borrow_mut();
+            cart.ppu_address(address);
+
+            match cart.ppu_read(cartridge_address(address)) {
+                Some(cartridge::MappedPpuAddress::Ciram(page)) => {
+                    return self.table_name[page][(address & 0x03FF) as usize]
+                }
+                Some(cartridge::MappedPpuAddress::Data(data)) => return data,
+                _ => {}
+            }
+        }
+
+        if (0x0000..=0x1FFF).contains(&address) {
             return self.table_pattern[((address & 0x1000) >> 12) as usize]
                 [(address & 0x0FFF) as usize];
         } else if (0x2000..=0x3EFF).contains(&address) {
@@ -392,7 +460,7 @@ impl PPU {
             let cart = self.cartridge.as_ref().unwrap().borrow();
             let index_addr = (address & 0x03FF) as usize;
 
-            match cart.mirror {
+            match cart.mirror() {
                 cartridge::Mirror::Vertical => {
                     if (0x0000..=0x03FF).contains(&address) {
                         return self.table_name[0][index_addr];
@@ -411,7 +479,8 @@ impl PPU {
                         return self.table_name[1][index_addr];
                     }
                 }
-                _ => todo!(),
+                cartridge::Mirror::OnescreenLo => return self.table_name[0][index_addr],
+                cartridge::Mirror::OnescreenHi => return self.table_name[1][index_addr],
             }
         } else if (0x3F00..=0x3FFF).contains(&address) {
             address &= 0x1F;
@@ -440,15 +509,21 @@ impl PPU {
     pub fn ppu_write(&mut self, mut address: u16, data: u8) {
         address &= 0x3FFF;
 
-        if self
-            .cartridge
-            .as_mut()
-            .unwrap()
-            .borrow_mut()
-            .ppu_write(address, data)
-            .is_some()
-        {
-        } else if (0x0000..=0x1FFF).contains(&address) {
+        if address < 0x3F00 {
+            let mut cart = self.cartridge.as_ref().unwrap().borrow_mut();
+            cart.ppu_address(address);
+
+            match cart.ppu_write(cartridge_address(address), data) {
+                Some(cartridge::MappedPpuAddress::Ciram(page)) => {
+                    self.table_name[page][(address & 0x03FF) as usize] = data;
+                    return;
+                }
+                Some(cartridge::MappedPpuAddress::Data(_)) => return,
+                _ => {}
+            }
+        }
+
+        if (0x0000..=0x1FFF).contains(&address) {
             self.table_pattern[((address & 0x1000) >> 12) as usize][(address & 0x0FFF) as usize] =
                 data;
         } else if (0x2000..=0x3EFF).contains(&address) {
@@ -457,7 +532,7 @@ impl PPU {
             let cart = self.cartridge.as_ref().unwrap().borrow();
             let index_addr = (address & 0x03FF) as usize;
 
-            match cart.mirror {
+            match cart.mirror() {
                 cartridge::Mirror::Vertical => {
                     if (0x0000..=0x03FF).contains(&address) {
                         self.table_name[0][index_addr] = data;
@@ -476,7 +551,8 @@ impl PPU {
                         self.table_name[1][index_addr] = data;
                     }
                 }
-                _ => todo!(),
+                cartridge::Mirror::OnescreenLo => self.table_name[0][index_addr] = data,
+                cartridge::Mirror::OnescreenHi => self.table_name[1][index_addr] = data,
             }
         } else if (0x3F00..=0x3FFF).contains(&address) {
             address &= 0x1F;
@@ -499,7 +575,7 @@ impl PPU {
     #[allow(dead_code)]
     pub fn tick(&mut self) {
         fn inc_scroll_x(ppu: &mut PPU) {
-            if !ppu.mask.contains(PPUMask::REND_BG) || !ppu.mask.contains(PPUMask::REND_SPR) {
+            if !ppu.rendering_enabled() {
                 return;
             }
 
@@ -513,7 +589,7 @@ impl PPU {
         }
 
         fn inc_scroll_y(ppu: &mut PPU) {
-            if !ppu.mask.contains(PPUMask::REND_BG) || !ppu.mask.contains(PPUMask::REND_SPR) {
+            if !ppu.rendering_enabled() {
                 return;
             }
 
@@ -536,7 +612,7 @@ impl PPU {
         }
 
         fn mv_addr_x(ppu: &mut PPU) {
-            if !ppu.mask.contains(PPUMask::REND_BG) || !ppu.mask.contains(PPUMask::REND_SPR) {
+            if !ppu.rendering_enabled() {
                 return;
             }
 
@@ -546,7 +622,7 @@ impl PPU {
         }
 
         fn mv_addr_y(ppu: &mut PPU) {
-            if !ppu.mask.contains(PPUMask::REND_BG) || !ppu.mask.contains(PPUMask::REND_SPR) {
+            if !ppu.rendering_enabled() {
                 return;
             }
 
@@ -577,17 +653,116 @@ impl PPU {
         }
 
         fn update_shifters(ppu: &mut PPU) {
-            if !ppu.mask.contains(PPUMask::REND_BG) {
+            if ppu.mask.contains(PPUMask::REND_BG) {
+                // Shifting background tile pattern row
+                ppu.bg_shifter_pattern_lo <<= 1;
+                ppu.bg_shifter_pattern_hi <<= 1;
+
+                // Shifting palette attributes by 1
+                ppu.bg_shifter_attrib_lo <<= 1;
+                ppu.bg_shifter_attrib_hi <<= 1;
+            }
+
+            // Sprites wait for their x counter to run out before shifting out pixels
+            if ppu.mask.contains(PPUMask::REND_SPR) && ppu.cycle < 258 {
+                for i in 0..ppu.sprite_count as usize {
+                    if ppu.spr_x_counter[i] > 0 {
+                        ppu.spr_x_counter[i] -= 1;
+                    } else {
+                        ppu.spr_shifter_pattern_lo[i] <<= 1;
+                        ppu.spr_shifter_pattern_hi[i] <<= 1;
+                    }
+                }
+            }
+        }
+
+        fn evaluate_sprites(ppu: &mut PPU) {
+            ppu.secondary_oam = [0xFF; 32];
+            ppu.sprite_count = 0;
+            ppu.spr_zero_hit_possible = false;
+
+            // The pre-render line never has sprites ready for scanline 0
+            if ppu.scanline < 0 {
                 return;
             }
 
-            // Shifting background tile pattern row
-            ppu.bg_shifter_pattern_lo <<= 1;
-            ppu.bg_shifter_pattern_hi <<= 1;
+            let height = ppu.sprite_height();
+
+            let mut n = 0;
+            while n < 64 && ppu.sprite_count < 8 {
+                let row = ppu.scanline - ppu.oam[n * 4] as i16;
+
+                if (0..height).contains(&row) {
+                    if n == 0 {
+                        ppu.spr_zero_hit_possible = true;
+                    }
+
+                    let slot = ppu.sprite_count as usize * 4;
+                    ppu.secondary_oam[slot..slot + 4].copy_from_slice(&ppu.oam[n * 4..n * 4 + 4]);
+                    ppu.sprite_count += 1;
+                }
+
+                n += 1;
+            }
+
+            // Once secondary OAM is full the hardware keeps scanning, but it wrongly
+            // increments the byte offset along with the sprite index on every miss
+            let mut m = 0;
+            while n < 64 {
+                let row = ppu.scanline - ppu.oam[n * 4 + m] as i16;
+
+                if (0..height).contains(&row) {
+                    ppu.status.set(PPUStatus::SPR_OVERFLOW, true);
+                    break;
+                }
+
+                n += 1;
+                m = (m + 1) & 0x03;
+            }
+        }
+
+        fn fetch_sprite(ppu: &mut PPU, index: usize, plane_hi: bool) {
+            let entry = &ppu.secondary_oam[index * 4..index * 4 + 4];
+            let (y, id, attrib, x) = (entry[0], entry[1], entry[2], entry[3]);
+
+            // Empty slots still fetch tile $FF, the result is simply discarded
+            let active = index < ppu.sprite_count as usize;
+            let height = ppu.sprite_height() as u16;
+            let mut row = if active {
+                (ppu.scanline - y as i16) as u16 & (height - 1)
+            } else {
+                0
+            };
+
+            // Vertical flip spans both tiles of a tall sprite
+            if attrib & 0x80 != 0 {
+                row = height - 1 - row;
+            }
 
-            // Shifting palette attributes by 1
-            ppu.bg_shifter_attrib_lo <<= 1;
-            ppu.bg_shifter_attrib_hi <<= 1;
+            let (table, tile) = if height == 16 {
+                // 8x16 sprites pick their pattern table from bit 0 of the tile index
+                ((id & 0x01) as u16, ((id & 0xFE) as u16) + (row >> 3))
+            } else {
+                (ppu.control.contains(PPUControl::PTRN_SPR) as u16, id as u16)
+            };
+
+            let address = (table << 12) | (tile << 4) | (row & 0x07) | if plane_hi { 8 } else { 0 };
+
+            let mut data = ppu.ppu_read(address);
+
+            if !active {
+                data = 0x00;
+            } else if attrib & 0x40 != 0 {
+                data = data.reverse_bits();
+            }
+
+            if plane_hi {
+                ppu.spr_shifter_pattern_hi[index] = data;
+            } else {
+                ppu.spr_shifter_pattern_lo[index] = data;
+                ppu.spr_attrib[index] = attrib;
+                ppu.spr_x_counter[index] = x;
+            }
         }
 
         if (-1..240).contains(&self.scanline) {
@@ -597,9 +772,14 @@ impl PPU {
 
             if self.scanline == -1 && self.cycle == 0 {
                 self.status.set(PPUStatus::V_BLANK, false);
+                self.status.set(PPUStatus::SPR_0_HIT, false);
+                self.status.set(PPUStatus::SPR_OVERFLOW, false);
             }
 
-            if (2..258).contains(&self.cycle) || (321..338).contains(&self.cycle) {
+            // With rendering off the PPU leaves the bus alone, mappers snooping it
+            // (MMC3's A12 counter, the MMC2/MMC4/MMC5 latches) must not see fetches either
+            let fetching = (2..258).contains(&self.cycle) || (321..338).contains(&self.cycle);
+            if fetching && self.rendering_enabled() {
                 update_shifters(self);
 
                 match (self.cycle - 1) % 8 {
@@ -653,9 +833,25 @@ impl PPU {
             if self.cycle == 257 {
                 load_bg_shifters(self);
                 mv_addr_x(self);
+
+                if self.rendering_enabled() {
+                    evaluate_sprites(self);
+                }
             }
 
-            if self.cycle == 338 || self.cycle == 340 {
+            if (257..321).contains(&self.cycle) && self.rendering_enabled() {
+                self.oam_addr = 0x00;
+
+                let index = ((self.cycle - 257) / 8) as usize;
+
+                match (self.cycle - 257) % 8 {
+                    5 => fetch_sprite(self, index, false),
+                    7 => fetch_sprite(self, index, true),
+                    _ => {}
+                }
+            }
+
+            if (self.cycle == 338 || self.cycle == 340) && self.rendering_enabled() {
                 self.bg_next_tile_id = self.ppu_read(0x2000 | (self.vram_addr.get_data() & 0x0FFF));
             }
 
@@ -735,11 +931,69 @@ impl PPU {
             bg_palette = (bg_pal1 << 1) | bg_pal0;
         }
 
+        let mut fg_pixel = 0x00_u8;
+        let mut fg_palette = 0x00_u8;
+        let mut fg_priority = false;
+
+        if self.mask.contains(PPUMask::REND_SPR) {
+            // Lower OAM indices win, so the first opaque sprite pixel is the one drawn
+            for i in 0..self.sprite_count as usize {
+                if self.spr_x_counter[i] != 0 {
+                    continue;
+                }
+
+                let p0_pixel = ((self.spr_shifter_pattern_lo[i] & 0x80) > 0) as u8;
+                let p1_pixel = ((self.spr_shifter_pattern_hi[i] & 0x80) > 0) as u8;
+                fg_pixel = (p1_pixel << 1) | p0_pixel;
+
+                if fg_pixel != 0 {
+                    fg_palette = (self.spr_attrib[i] & 0x03) + 0x04;
+                    fg_priority = (self.spr_attrib[i] & 0x20) == 0;
+                    break;
+                }
+            }
+        }
+
         let (x, y) = ((self.cycle - 1) as i32, self.scanline as i32);
 
-        // sprScreen->SetPixel(cycle - 1, scanline, GetColourFromPaletteRam(bg_palette, bg_pixel));
+        if x < 8 {
+            if !self.mask.contains(PPUMask::REND_BG_LEFT) {
+                bg_pixel = 0x00;
+            }
+            if !self.mask.contains(PPUMask::REND_SPR_LEFT) {
+                fg_pixel = 0x00;
+            }
+        }
+
+        // Sprite zero hits on any opaque overlap, even when another sprite is drawn on top
+        if self.spr_zero_hit_possible
+            && self.spr_x_counter[0] == 0
+            && self.mask.contains(PPUMask::REND_BG | PPUMask::REND_SPR)
+            && bg_pixel != 0
+            && (0..PAL_WIDTH - 1).contains(&x)
+            && (x >= 8
+                || self
+                    .mask
+                    .contains(PPUMask::REND_BG_LEFT | PPUMask::REND_SPR_LEFT))
+        {
+            let spr_zero_pixel =
+                (self.spr_shifter_pattern_lo[0] & 0x80) | (self.spr_shifter_pattern_hi[0] & 0x80);
+
+            if spr_zero_pixel != 0 && (0..PAL_HEIGHT).contains(&y) {
+                self.status.set(PPUStatus::SPR_0_HIT, true);
+            }
+        }
+
+        let (pixel, palette) = match (bg_pixel, fg_pixel) {
+            (0, 0) => (0x00, 0x00),
+            (0, _) => (fg_pixel, fg_palette),
+            (_, 0) => (bg_pixel, bg_palette),
+            _ if fg_priority => (fg_pixel, fg_palette),
+            _ => (bg_pixel, bg_palette),
+        };
+
         if (0..PAL_WIDTH).contains(&x) && (0..PAL_HEIGHT).contains(&y) {
-            self.buf[y as usize][x as usize] = self.get_color(bg_palette, bg_pixel);
+            self.buf[y as usize][x as usize] = self.get_color(palette, pixel);
         }
 
         self.cycle += 1;
@@ -1,15 +1,98 @@
-use cpu::PAL_PALETTE;
+use cpu::{Buttons, PAL_PALETTE};
 use raylib::prelude::*;
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, ffi::c_void, fs, path::Path, rc::Rc, time::Duration};
+
+const ROM_PATH: &str = "nestest.nes";
 
 const PAL_WIDTH: i32 = 256;
 const PAL_HEIGHT: i32 = 240;
 const SCALE: i32 = 3;
 
+const SAMPLE_RATE: u32 = 44_100;
+/// Raylib splits the stream buffer in two halves and only accepts whole halves
+const AUDIO_SUB_BUFFER: usize = 1024;
+/// Amount of queued audio we try to sit at, in samples
+const AUDIO_TARGET_FILL: usize = AUDIO_SUB_BUFFER * 2;
+/// Largest resampling rate change dynamic rate control may apply
+const MAX_RATE_DELTA: f64 = 0.005;
+/// How long to wait for the device to play some audio when the queue is backed up
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+const PLAYER_1_KEYS: [(KeyboardKey, Buttons); 8] = [
+    (KeyboardKey::KEY_X, Buttons::A),
+    (KeyboardKey::KEY_Z, Buttons::B),
+    (KeyboardKey::KEY_RIGHT_SHIFT, Buttons::SELECT),
+    (KeyboardKey::KEY_ENTER, Buttons::START),
+    (KeyboardKey::KEY_UP, Buttons::UP),
+    (KeyboardKey::KEY_DOWN, Buttons::DOWN),
+    (KeyboardKey::KEY_LEFT, Buttons::LEFT),
+    (KeyboardKey::KEY_RIGHT, Buttons::RIGHT),
+];
+
+const PLAYER_2_KEYS: [(KeyboardKey, Buttons); 8] = [
+    (KeyboardKey::KEY_M, Buttons::A),
+    (KeyboardKey::KEY_N, Buttons::B),
+    (KeyboardKey::KEY_U, Buttons::SELECT),
+    (KeyboardKey::KEY_O, Buttons::START),
+    (KeyboardKey::KEY_I, Buttons::UP),
+    (KeyboardKey::KEY_K, Buttons::DOWN),
+    (KeyboardKey::KEY_J, Buttons::LEFT),
+    (KeyboardKey::KEY_L, Buttons::RIGHT),
+];
+
+fn held_buttons(rl: &RaylibHandle, keys: &[(KeyboardKey, Buttons)]) -> Buttons {
+    keys.iter()
+        .filter(|(key, _)| rl.is_key_down(*key))
+        .fold(Buttons::empty(), |buttons, (_, button)| buttons | *button)
+}
+
+fn run_frame(nes: &mut cpu::NES, found_demo: &mut bool) {
+    loop {
+        nes.tick();
+
+        if nes.program_counter() == 0xC74B && !*found_demo {
+            println!("Demo");
+            *found_demo = true;
+        }
+
+        if nes.ppu.frame_complete {
+            break;
+        }
+    }
+    nes.ppu.frame_complete = false;
+}
+
+fn pump_audio(audio: &mut RaylibAudio, stream: &AudioStream, queue: &mut Vec<f32>) {
+    while queue.len() >= AUDIO_SUB_BUFFER && audio.is_audio_stream_processed(stream) {
+        // AudioStream::update_audio_stream passes a byte count where raylib expects samples
+        unsafe {
+            raylib::ffi::UpdateAudioStream(
+                *stream.as_ref(),
+                queue.as_ptr() as *const c_void,
+                AUDIO_SUB_BUFFER as i32,
+            );
+        }
+        queue.drain(..AUDIO_SUB_BUFFER);
+    }
+}
+
 fn main() {
-    let cart = cartridge::Cartridge::new("nestest.nes".to_string());
+    // Battery backed memory lives next to the ROM
+    let save_path = Path::new(ROM_PATH).with_extension("sav");
+
+    let cart = match cartridge::Cartridge::new(ROM_PATH.to_string()) {
+        Ok(cart) => Rc::new(RefCell::new(cart)),
+        Err(err) => {
+            eprintln!("Couldn't load {ROM_PATH}: {err}");
+            std::process::exit(1);
+        }
+    };
+    if let Ok(data) = fs::read(&save_path) {
+        cart.borrow_mut().load_save_data(&data);
+    }
+
     let mut nes = cpu::NES::default();
-    nes.attach_cart(Rc::new(RefCell::new(cart)));
+    nes.attach_cart(Rc::clone(&cart));
     nes.reset();
 
     // loop {
@@ -20,7 +103,6 @@ fn main() {
     //     }
     // }
 
-    let mut residual_time = 0.0_f32;
     let mut emulation_run = false;
     let mut palette = 0;
     let mut found_demo = false;
@@ -35,32 +117,50 @@ fn main() {
         ..Default::default()
     };
 
+    // Only while paused, once the emulation runs the audio device paces it
     rl.set_target_fps(60);
 
+    let mut audio = RaylibAudio::init_audio_device();
+    unsafe {
+        raylib::ffi::SetAudioStreamBufferSizeDefault((AUDIO_SUB_BUFFER * 2) as i32);
+    }
+    let mut stream = AudioStream::init_audio_stream(&thread, SAMPLE_RATE, 32, 1);
+    audio.play_audio_stream(&mut stream);
+    audio.pause_audio_stream(&mut stream);
+
+    nes.apu.set_sample_rate(SAMPLE_RATE);
+    let mut audio_queue: Vec<f32> = Vec::with_capacity(AUDIO_TARGET_FILL * 4);
+    let mut audio_scratch = vec![0.0_f32; SAMPLE_RATE as usize];
+
     while !rl.window_should_close() {
         let fps = rl.get_fps();
         let key = rl.get_key_pressed();
 
+        nes.set_buttons(0, held_buttons(&rl, &PLAYER_1_KEYS));
+        nes.set_buttons(1, held_buttons(&rl, &PLAYER_2_KEYS));
+
         if emulation_run {
-            let delta = rl.get_frame_time();
-            if residual_time > 0.0 {
-                residual_time -= delta;
+            // Audio is the only clock: run frames until the queue reaches the target, an extra
+            // one when the device is about to starve, then let rate control absorb the rest
+            if audio_queue.len() < AUDIO_TARGET_FILL {
+                run_frame(&mut nes, &mut found_demo);
             } else {
-                residual_time += (1.0 / 60.0) - delta;
-                loop {
-                    nes.tick();
+                std::thread::sleep(AUDIO_POLL_INTERVAL);
+            }
+            if audio_queue.len() < AUDIO_SUB_BUFFER {
+                run_frame(&mut nes, &mut found_demo);
+            }
 
-                    if nes.program_counter() == 0xC74B && !found_demo {
-                        println!("Demo");
-                        found_demo = true;
-                    }
+            let count = nes.drain_audio(&mut audio_scratch);
+            audio_queue.extend_from_slice(&audio_scratch[..count]);
 
-                    if nes.ppu.frame_complete {
-                        break;
-                    }
-                }
-                nes.ppu.frame_complete = false;
-            }
+            pump_audio(&mut audio, &stream, &mut audio_queue);
+
+            // No adjustment at the target, the full one at an empty queue or twice the target
+            let error =
+                (audio_queue.len() as f64 - AUDIO_TARGET_FILL as f64) / AUDIO_TARGET_FILL as f64;
+            nes.apu
+                .set_rate_adjustment(-MAX_RATE_DELTA * error.clamp(-1.0, 1.0));
         } else if let Some(input) = key {
             match input {
                 raylib::consts::KeyboardKey::KEY_C => {
@@ -107,6 +207,15 @@ fn main() {
             match input {
                 raylib::consts::KeyboardKey::KEY_SPACE => {
                     emulation_run = !emulation_run;
+
+                    // A frame cap would be a second clock fighting the audio one
+                    if emulation_run {
+                        rl.set_target_fps(0);
+                        audio.resume_audio_stream(&mut stream);
+                    } else {
+                        rl.set_target_fps(60);
+                        audio.pause_audio_stream(&mut stream);
+                    }
                 }
                 raylib::consts::KeyboardKey::KEY_R => {
                     nes.reset();
@@ -176,7 +285,11 @@ fn main() {
         );
     }
 
-    use std::fs;
+    if let Some(data) = cart.borrow().save_data() {
+        if let Err(err) = fs::write(&save_path, data) {
+            eprintln!("Couldn't write {}: {err}", save_path.display());
+        }
+    }
 
     fs::write("log_dognes.txt", nes.ppu.log.join("\n")).expect("");
 }